@@ -1,6 +1,12 @@
-use super::config::Config;
-use super::metrics::Metrics;
+use super::census::HeapCensus;
+use super::config::{Config, ConfigError};
+use super::event::GcEventListener;
+use super::gc::Gc;
+use super::heap_dump::{HeapDump, RetentionPath};
+use super::metrics::{Metrics, MetricsSnapshot};
 use super::mutator::Mutator;
+use super::policy::CollectionDecision;
+use super::stress::StressPoint;
 use super::trace::Trace;
 use crate::trace::Collector;
 
@@ -31,7 +37,7 @@ where
     collector: Arc<Collector>,
     root: Box<R::Of<'static>>,
     #[cfg(feature = "multi_threaded")]
-    monitor_thread: Option<std::thread::JoinHandle<()>>,
+    monitor_thread: std::sync::Mutex<Option<std::thread::JoinHandle<()>>>,
 }
 
 impl<R: ForLt + 'static> Arena<R>
@@ -68,16 +74,28 @@ where
         Self::new_with_config(config, f)
     }
 
-    // eventually it would be cool to allow the user to pass in their own config
+    /// Same as [`Arena::new`], but with the given Config instead of
+    /// [`Config::default`].
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the Config is invalid, see [`Config::validate`]. A Config
+    /// built with [`crate::ConfigBuilder`] has already been validated.
     pub fn new_with_config<F>(config: Config, f: F) -> Self
     where
         F: for<'gc> FnOnce(&'gc Mutator<'gc>) -> R::Of<'gc>,
     {
+        if let Err(err) = config.validate() {
+            panic!("invalid config: {err}");
+        }
+
         // This is function is extremely sketchy.
         //
         // I have a very fragile understanding of whats going on here, and
         // I truly don't know whether this is safe.
 
+        #[cfg(feature = "multi_threaded")]
+        let monitor_on = config.monitor_on;
         let collector = Arc::new(Collector::new(config));
 
         let collector_ref: &'static Collector = unsafe { &*(&*collector as *const Collector) };
@@ -91,13 +109,17 @@ where
 
         #[cfg(feature = "multi_threaded")]
         {
-            let monitor_thread = collector.clone().spawn_monitor_thread(root.as_ref());
-
-            Self {
+            let arena = Self {
                 collector,
                 root,
-                monitor_thread,
+                monitor_thread: std::sync::Mutex::new(None),
+            };
+
+            if monitor_on {
+                arena.start_monitor();
             }
+
+            arena
         }
 
         #[cfg(not(feature = "multi_threaded"))]
@@ -151,37 +173,105 @@ where
     /// });
     ///
     /// ```
-    pub fn mutate<F>(&self, f: F)
+    ///
+    /// ## Returning Values
+    ///
+    /// A mutation may return any value which is not branded with the `'gc`
+    /// lifetime.
+    ///
+    /// ```
+    /// # use sandpit::{Arena, Root, Gc};
+    /// #
+    /// # let arena: Arena<Root![Gc<'_, usize>]> = Arena::new(|mu| {
+    /// #    Gc::new(mu, 42)
+    /// # });
+    /// let exit_code = arena.mutate(|mu, root| **root + 1);
+    ///
+    /// assert_eq!(exit_code, 43);
+    /// ```
+    ///
+    /// Attempting to return a Gc pointer will fail to compile.
+    ///
+    /// ```compile_fail
+    /// # use sandpit::{Arena, Root, Gc};
+    /// #
+    /// # let arena: Arena<Root![Gc<'_, usize>]> = Arena::new(|mu| {
+    /// #    Gc::new(mu, 42)
+    /// # });
+    /// let escaped = arena.mutate(|mu, root| root.clone());
+    /// ```
+    pub fn mutate<F, T>(&self, f: F) -> T
     where
-        F: for<'gc> FnOnce(&'gc Mutator<'gc>, &'gc R::Of<'gc>),
+        F: for<'gc> FnOnce(&'gc Mutator<'gc>, &'gc R::Of<'gc>) -> T,
     {
+        self.collect_for_heap_limit();
+
         let mutator = self.new_mutator();
         let root = unsafe { self.scoped_root() };
 
-        f(&mutator, root);
+        let result = f(&mutator, root);
+
+        drop(mutator);
+        self.collector
+            .stress_trigger(&self.collector.config(), StressPoint::MutateExit);
 
         // In single-threaded mode, check if we should collect before exiting mutation
         #[cfg(not(feature = "multi_threaded"))]
-        {
-            drop(mutator);
+        let decision = self.collector.collection_trigger();
+        // Otherwise collections are left to the monitor, other than those
+        // requested by the stress mode
+        #[cfg(feature = "multi_threaded")]
+        let decision = self.collector.stress_pending();
 
-            if self.collector.major_trigger() {
-                self.major_collect();
-            } else if self.collector.minor_trigger() {
-                self.minor_collect();
-            }
+        match decision {
+            CollectionDecision::Major => self.major_collect(),
+            CollectionDecision::Minor => self.minor_collect(),
+            CollectionDecision::None => {}
         }
+
+        result
+    }
+
+    /// Same as [`Arena::mutate`], but for mutations which may fail.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use sandpit::{Arena, Root, Gc};
+    ///
+    /// let arena: Arena<Root![Gc<'_, usize>]> = Arena::new(|mu| {
+    ///     Gc::new(mu, 0)
+    /// });
+    ///
+    /// let result: Result<(), String> = arena.try_mutate(|mu, root| {
+    ///     if **root == 0 {
+    ///         return Err("division by zero".to_string());
+    ///     }
+    ///
+    ///     Ok(())
+    /// });
+    ///
+    /// assert!(result.is_err());
+    /// ```
+    pub fn try_mutate<F, T, E>(&self, f: F) -> Result<T, E>
+    where
+        F: for<'gc> FnOnce(&'gc Mutator<'gc>, &'gc R::Of<'gc>) -> Result<T, E>,
+    {
+        self.mutate(f)
     }
 
     /// you can view the root but you don't have a mutator, therefore collection
     /// can happen while viewing
+    ///
+    /// Collections which occur while viewing do not compact the arena, as
+    /// doing so would move the values being viewed.
     pub fn view<F>(&self, f: F)
     where
         F: for<'gc> FnOnce(&'gc R::Of<'gc>),
     {
         let root = unsafe { self.scoped_root() };
 
-        f(root);
+        self.collector.view(|| f(root));
     }
 
     /// Synchronously trigger a major collection. A major collection means that
@@ -195,6 +285,100 @@ where
         self.collector.major_collect(self.root.as_ref());
     }
 
+    /// Synchronously run a major collection which counts the number and size
+    /// of the live values of each type found by its trace.
+    ///
+    /// Values allocated while the collection is in progress are live, but are
+    /// not counted, as they are not traced.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sandpit::{Arena, Root, Gc};
+    ///
+    /// let arena: Arena<Root![Gc<'_, [Gc<'_, usize>]>]> = Arena::new(|mu| {
+    ///     mu.alloc_array_from_fn(10, |i| Gc::new(mu, i))
+    /// });
+    ///
+    /// let census = arena.heap_census();
+    ///
+    /// assert_eq!(census.get("usize").unwrap().count, 10);
+    ///
+    /// for ty in census.types() {
+    ///     println!("{}: {} values, {} bytes", ty.type_name, ty.count, ty.bytes);
+    /// }
+    /// ```
+    pub fn heap_census(&self) -> HeapCensus {
+        self.collector.heap_census(self.root.as_ref())
+    }
+
+    /// Synchronously run a major collection which records the graph of live
+    /// values reachable from the root, along with every pointer traced
+    /// between them. The dump can be exported with [`HeapDump::to_json`] or
+    /// [`HeapDump::to_dot`].
+    ///
+    /// The collection does not compact the arena, even if
+    /// [`Config::compaction_on`] is set, so the recorded addresses remain
+    /// valid until the next collection. As with [`Arena::heap_census`],
+    /// values allocated while the collection is in progress are not recorded.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sandpit::{Arena, Root, Gc};
+    ///
+    /// let arena: Arena<Root![Gc<'_, [Gc<'_, usize>]>]> = Arena::new(|mu| {
+    ///     mu.alloc_array_from_fn(3, |i| Gc::new(mu, i))
+    /// });
+    ///
+    /// let dump = arena.heap_dump();
+    ///
+    /// // the array and each of its elements
+    /// assert_eq!(dump.nodes.len(), 4);
+    /// // the root's pointer to the array, and the array's pointer to each element
+    /// assert_eq!(dump.edges.len(), 4);
+    ///
+    /// // the dump may be rendered with graphviz
+    /// let dot = dump.to_dot();
+    /// # assert!(dot.starts_with("digraph heap {"));
+    /// ```
+    pub fn heap_dump(&self) -> HeapDump {
+        self.collector.heap_dump(self.root.as_ref())
+    }
+
+    /// Synchronously run a major collection which records why `gc` is still
+    /// alive, returning a shortest chain of values through which it is
+    /// reachable from the root. Returns `None` if `gc` was not reached by the
+    /// collection, such as if it was allocated while the collection was in
+    /// progress.
+    ///
+    /// Like [`Arena::heap_dump`], the collection does not compact the arena,
+    /// so `gc` remains valid. This must not be called from within
+    /// [`Arena::mutate`], as the collection would wait on the calling
+    /// mutator, but it may be called from [`Arena::view`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use sandpit::{Arena, Root, Gc};
+    ///
+    /// let arena: Arena<Root![Gc<'_, [Gc<'_, usize>]>]> = Arena::new(|mu| {
+    ///     mu.alloc_array_from_fn(3, |i| Gc::new(mu, i))
+    /// });
+    ///
+    /// arena.view(|root| {
+    ///     let path = arena.retention_path(&root[1]).unwrap();
+    ///
+    ///     assert_eq!(path.nodes.len(), 2);
+    ///     assert_eq!(path.nodes[1].type_name, "usize");
+    ///
+    ///     // root -> [sandpit::gc::Gc<usize>] (0x...) -> usize (0x...)
+    ///     println!("{path}");
+    /// });
+    /// ```
+    pub fn retention_path<T: Trace + ?Sized>(&self, gc: &Gc<'_, T>) -> Option<RetentionPath> {
+        let address = gc.as_thin().as_ptr() as usize;
+
+        self.heap_dump().retention_path(address)
+    }
+
     /// Synchronously trigger a minor collection. A minor collection will only
     /// trace *new* objects, which are objects that have been allocated since
     /// the last collection. It will likely take less time than a major collection,
@@ -210,25 +394,156 @@ where
     /// Starts a monitor in a separate thread if it is not already started.
     /// The monitor will automatically and concurrently trigger major and
     /// minor collections when appropriate.
-    /*
+    ///
+    /// In single-threaded mode there is no monitor thread, instead starting
+    /// the monitor enables collections to be triggered at the end of
+    /// each mutation.
+    ///
+    /// The monitor is started when the arena is created if
+    /// [`Config::monitor_on`] is set.
     pub fn start_monitor(&self) {
-        self.monitor.clone().start();
+        #[cfg(feature = "multi_threaded")]
+        {
+            let mut monitor_thread = self.monitor_thread.lock().unwrap();
+
+            if monitor_thread.is_none() {
+                *monitor_thread = Some(self.collector.clone().spawn_monitor_thread(self.root.as_ref()));
+            }
+        }
+
+        #[cfg(not(feature = "multi_threaded"))]
+        self.collector.start_monitor();
     }
 
     /// Signal for the monitor thread to stop, and block until it does so.
+    /// Collections will then only occur when explicitly requested, until the
+    /// monitor is started again.
+    ///
+    /// If the monitor is in the middle of a collection, this blocks until
+    /// the collection completes.
     pub fn stop_monitor(&self) {
-        self.monitor.stop();
+        #[cfg(feature = "multi_threaded")]
+        {
+            let mut monitor_thread = self.monitor_thread.lock().unwrap();
+
+            if let Some(handle) = monitor_thread.take() {
+                self.collector.stop_monitor();
+                let _ = handle.join();
+            }
+        }
+
+        #[cfg(not(feature = "multi_threaded"))]
+        self.collector.stop_monitor();
+    }
+
+    /// Pause the monitor, preventing it from triggering any collections until
+    /// [`Arena::resume_monitor`] is called. Unlike stopping the monitor this
+    /// does not block, and a collection which is already in progress will
+    /// still run to completion.
+    ///
+    /// Pauses nest, the monitor will only resume once every call to
+    /// `pause_monitor` has been matched with a call to `resume_monitor`.
+    /// Explicit calls to [`Arena::major_collect`] and [`Arena::minor_collect`]
+    /// are unaffected.
+    pub fn pause_monitor(&self) {
+        self.collector.pause_monitor();
     }
-    */
-
-    /// Returns a copy of the the Config that the arena was created with.
-    /// Currently there is no way to update the Config after the arena
-    /// has been created.
-    /*
-    fn get_config(&self) -> Config {
-        self.config
+
+    /// Resume a monitor paused by [`Arena::pause_monitor`]. Has no effect if
+    /// the monitor is not paused.
+    pub fn resume_monitor(&self) {
+        self.collector.resume_monitor();
+    }
+
+    /// Runs `f` with the monitor paused, resuming it once `f` returns or
+    /// panics. Useful for keeping automatic collections out of latency
+    /// critical sections.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sandpit::{Arena, Root, Gc};
+    ///
+    /// let arena: Arena<Root![Gc<'_, usize>]> = Arena::new(|mu| Gc::new(mu, 42));
+    ///
+    /// let frame = arena.with_gc_disabled(|| {
+    ///     arena.mutate(|mu, root| {
+    ///         // no collection will be triggered by this allocation
+    ///         let value = Gc::new(mu, **root + 1);
+    ///
+    ///         *value
+    ///     })
+    /// });
+    ///
+    /// assert_eq!(frame, 43);
+    /// ```
+    pub fn with_gc_disabled<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        struct ResumeGuard<'a>(&'a Collector);
+
+        impl Drop for ResumeGuard<'_> {
+            fn drop(&mut self) {
+                self.0.resume_monitor();
+            }
+        }
+
+        self.collector.pause_monitor();
+        let _guard = ResumeGuard(&self.collector);
+
+        f()
+    }
+
+    /// Returns a copy of the Config the arena is currently using.
+    pub fn config(&self) -> Config {
+        self.collector.config()
+    }
+
+    /// Update the arena's Config via a callback which is given a copy of the
+    /// current Config to modify.
+    ///
+    /// The updated Config is validated with [`Config::validate`], and
+    /// discarded if it is invalid.
+    ///
+    /// If a collection is in progress the update is applied once it has
+    /// completed, otherwise it is applied immediately. Until then
+    /// [`Arena::config`] continues to return the previous Config, and further
+    /// updates are made on top of the pending one. Mutations which are
+    /// already active continue to use the Config they began with, until they
+    /// are parked by [`Mutator::safepoint`].
+    ///
+    /// Changing [`Config::tracer_threads`] or [`Config::concurrent_sweep`]
+    /// replaces the arena's tracer or sweeper threads when the update is
+    /// applied. [`Config::monitor_on`] is only read when the arena is
+    /// created, use [`Arena::start_monitor`] and [`Arena::stop_monitor`]
+    /// instead.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sandpit::{Arena, Root, Gc};
+    ///
+    /// let arena: Arena<Root![Gc<'_, usize>]> = Arena::new(|mu| Gc::new(mu, 42));
+    ///
+    /// arena.update_config(|config| {
+    ///     config.tracer_threads = 4;
+    ///     config.monitor_arena_size_ratio_trigger = 2.0;
+    /// })
+    /// .unwrap();
+    ///
+    /// assert_eq!(arena.config().tracer_threads, 4);
+    /// ```
+    pub fn update_config<F>(&self, f: F) -> Result<(), ConfigError>
+    where
+        F: FnOnce(&mut Config),
+    {
+        self.collector.update_config(f)
+    }
+
+    /// Register a listener which will receive each [`crate::GcEvent`] emitted
+    /// by the arena's GC. See [`GcEventListener`] for an example.
+    pub fn add_event_listener(&self, listener: Arc<dyn GcEventListener>) {
+        self.collector.add_event_listener(listener);
     }
-    */
 
     /// Returns a snap short of the GC's current metrics that provide information
     /// about how the GC is running.
@@ -236,6 +551,54 @@ where
         self.collector.metrics()
     }
 
+    /// Returns a copy of the GC's current metrics, including the
+    /// distribution of collection and pause times. Unlike reading the fields
+    /// of [`Arena::metrics`] individually, the values a collection records
+    /// together are always consistent with one another.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sandpit::{Arena, Root, Gc};
+    ///
+    /// let arena: Arena<Root![Gc<'_, usize>]> = Arena::new(|mu| Gc::new(mu, 42));
+    ///
+    /// arena.major_collect();
+    ///
+    /// let snapshot = arena.metrics_snapshot();
+    ///
+    /// assert_eq!(snapshot.major_collections, 1);
+    /// assert_eq!(snapshot.major_pause_times.count, 1);
+    /// assert!(snapshot.major_pause_times.max <= snapshot.major_collection_times.max);
+    /// ```
+    pub fn metrics_snapshot(&self) -> MetricsSnapshot {
+        self.collector.metrics().snapshot()
+    }
+
+    // An arena which is nearing its limit, or in which an allocation would
+    // have exceeded it, runs a major collection before a mutation begins, as
+    // memory cannot be freed while a mutation is active. The headroom ratio's
+    // share of the limit is left for the mutation to allocate into. If other
+    // mutations are active, the collection runs once they have exited.
+    fn collect_for_heap_limit(&self) {
+        let config = self.collector.config();
+
+        let Some(max_heap_bytes) = config.max_heap_bytes else {
+            return;
+        };
+
+        let threshold = max_heap_bytes as f64 / (1.0 + config.collector_max_headroom_ratio);
+
+        if !self.collector.is_major_requested()
+            && self.collector.metrics().estimated_heap_size() as f64 <= threshold
+        {
+            return;
+        }
+
+        if !self.collector.try_major_collect(self.root.as_ref()) {
+            self.collector.request_major_collection();
+        }
+    }
+
     // fingers crossed this works! lol
     unsafe fn scoped_root<'gc>(&self) -> &'gc R::Of<'gc> {
         core::mem::transmute::<&R::Of<'static>, &R::Of<'gc>>(self.root.as_ref())
@@ -252,13 +615,8 @@ where
     for<'a> <R as ForLt>::Of<'a>: Trace,
 {
     fn drop(&mut self) {
-        // Signal the monitor thread to shut down
-        self.collector.shutdown();
-
         // Join the monitor thread if it exists to ensure it shuts down
         // before the root is dropped
-        if let Some(handle) = self.monitor_thread.take() {
-            let _ = handle.join();
-        }
+        self.stop_monitor();
     }
 }
@@ -1,4 +1,4 @@
-use super::gc::{Gc, GcOpt};
+use super::gc::{Gc, GcOpt, GcWeak};
 use super::mutator::Mutator;
 use super::tagged::{Tag, Tagged};
 use super::trace::{Trace, Tracer};
@@ -116,6 +116,37 @@ impl<'gc, T: Trace + ?Sized> WriteBarrier<'gc, GcOpt<'gc, T>> {
     }
 }
 
+impl<'gc, T: Trace + ?Sized> WriteBarrier<'gc, GcWeak<'gc, T>> {
+    /// Update a [`GcWeak`] that is within a write barrier to point at a new value.
+    ///
+    /// ## Example
+    /// ```rust
+    /// use sandpit::{Arena, Gc, GcWeak, Root};
+    ///
+    /// let arena: Arena<Root![Gc<'_, GcWeak<'_, usize>>]> = Arena::new(|mu| {
+    ///    Gc::new(mu, GcWeak::new_none())
+    /// });
+    ///
+    /// arena.mutate(|mu, root| {
+    ///     let new_value = Gc::new(mu, 420);
+    ///
+    ///     root.write_barrier(mu, |barrier| {
+    ///         barrier.set(new_value.clone());
+    ///
+    ///         assert!(*barrier.inner().upgrade().unwrap() == 420);
+    ///     })
+    /// });
+    ///```
+    // SAFETY: A write barrier can only be safely obtained through
+    // the callback passed to `fn write_barrier` in which the object
+    // containing this pointer will be retraced
+    pub fn set(&self, gc: impl Into<GcWeak<'gc, T>>) {
+        unsafe {
+            self.inner.set(gc.into());
+        }
+    }
+}
+
 impl<'gc, T: Trace> WriteBarrier<'gc, [T]> {
     /// Get a writer barrier to a index of a slice behind write barrier.
     ///
@@ -173,6 +204,11 @@ impl<T: Trace> InnerBarrier<T> {
     }
 
     pub fn mark(&self, tracer: &mut Tracer) -> bool {
+        // heap verification must trace through every barrier it reaches
+        if tracer.is_verifying() {
+            return true;
+        }
+
         let mark = self.mark.load(Ordering::Acquire);
 
         if mark == tracer.get_mark().into() {
@@ -204,8 +240,7 @@ unsafe impl<T: Trace> Trace for InnerBarrier<T> {
     const IS_LEAF: bool = T::IS_LEAF;
 
     fn trace(&self, tracer: &mut Tracer) {
-        if self.mark.load(Ordering::Acquire) != tracer.get_mark().into() {
-            self.mark(tracer);
+        if self.mark(tracer) {
             self.inner.trace(tracer);
         }
     }
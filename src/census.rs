@@ -0,0 +1,86 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+/// The number and total size of the live values of each type in an arena,
+/// obtained by calling [`crate::Arena::heap_census`].
+///
+/// Sizes are in bytes and include each value's GC header.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HeapCensus {
+    types: Vec<TypeCensus>,
+}
+
+/// The live values of a single type counted by a [`HeapCensus`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TypeCensus {
+    /// The name of the type, as given by [`core::any::type_name`].
+    pub type_name: &'static str,
+    /// The number of live values of this type.
+    pub count: u64,
+    /// The total size of the live values of this type.
+    pub bytes: u64,
+}
+
+impl HeapCensus {
+    /// Returns the census of each type, ordered from the most to the least
+    /// bytes used.
+    pub fn types(&self) -> &[TypeCensus] {
+        &self.types
+    }
+
+    /// Returns the census of the type with the given name.
+    pub fn get(&self, type_name: &str) -> Option<&TypeCensus> {
+        self.types.iter().find(|census| census.type_name == type_name)
+    }
+
+    /// The total number of live values counted.
+    pub fn total_count(&self) -> u64 {
+        self.types.iter().map(|census| census.count).sum()
+    }
+
+    /// The total size of the live values counted.
+    pub fn total_bytes(&self) -> u64 {
+        self.types.iter().map(|census| census.bytes).sum()
+    }
+}
+
+// Aggregates the values marked by a tracer. Each tracer keeps its own, which
+// are merged once it has finished.
+#[derive(Default)]
+pub struct CensusRecorder {
+    types: BTreeMap<&'static str, (u64, u64)>,
+}
+
+impl CensusRecorder {
+    pub fn record(&mut self, type_name: &'static str, bytes: usize) {
+        let (count, total_bytes) = self.types.entry(type_name).or_default();
+
+        *count += 1;
+        *total_bytes += bytes as u64;
+    }
+
+    pub fn merge(&mut self, other: CensusRecorder) {
+        for (type_name, (count, bytes)) in other.types {
+            let (total_count, total_bytes) = self.types.entry(type_name).or_default();
+
+            *total_count += count;
+            *total_bytes += bytes;
+        }
+    }
+
+    pub fn finish(self) -> HeapCensus {
+        let mut types: Vec<TypeCensus> = self
+            .types
+            .into_iter()
+            .map(|(type_name, (count, bytes))| TypeCensus {
+                type_name,
+                count,
+                bytes,
+            })
+            .collect();
+
+        types.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.type_name.cmp(b.type_name)));
+
+        HeapCensus { types }
+    }
+}
@@ -1,8 +1,42 @@
-/// This structure contains the configuration settings for a garbage collector.
+use crate::policy::{CollectionPolicy, DefaultPolicy};
+use crate::stress::{StressMode, StressTrigger};
+use crate::Metrics;
+use alloc::sync::Arc;
+use core::fmt;
+
+/// Determines what happens when an allocation would grow an arena beyond
+/// [`Config::max_heap_bytes`].
+///
+/// A collection cannot free memory while a mutation is active, so an
+/// allocation which exceeds the limit cannot be satisfied by collecting in
+/// place. Instead, [`crate::Arena::mutate`] forces a major collection before
+/// starting a mutation once the arena nears its limit, so that garbage left
+/// by earlier mutations does not count against the allocations of the next.
+/// An allocation which exceeds the limit regardless requests a major
+/// collection, and should be retried in a later mutation.
 #[derive(Copy, Clone, Debug)]
+pub enum HeapLimitPolicy {
+    /// Fail the allocation. Fallible allocations such as
+    /// [`crate::Mutator::try_alloc`] return [`crate::AllocError::HeapLimitExceeded`],
+    /// while infallible allocations panic.
+    ///
+    /// With the `multi_threaded` feature, the major collection is only forced
+    /// before a mutation if no other mutation is active, otherwise it runs
+    /// once they have exited.
+    Fail,
+    /// Call the function with the arena's metrics and the size of the
+    /// requested allocation. The allocation proceeds if it returns true, and
+    /// fails as with [`HeapLimitPolicy::Fail`] otherwise.
+    Callback(fn(&Metrics, usize) -> bool),
+}
+
+/// This structure contains the configuration settings for a garbage collector.
+#[derive(Clone, Debug)]
 pub struct Config {
     /// The number of tracer threads, not including the thread that is used for
-    /// monitoring.
+    /// monitoring. Tracer threads are spawned when the arena is created, and
+    /// are kept alive until it is dropped. At least one tracer thread is
+    /// always used.
     pub tracer_threads: usize,
     /// The amount of work a tracer does before attempting to share its work.
     pub trace_chunk_size: usize,
@@ -10,9 +44,14 @@ pub struct Config {
     pub trace_share_min: usize,
     /// The percent of work a tracer shares when sharing its work.
     pub trace_share_ratio: f32,
-    /// The amount of miliseconds a tracer will wait for work before checking again or finishing its trace.
+    /// The most miliseconds an idle tracer will wait for mutators to stop
+    /// before checking for work again. Idle tracers are otherwise woken as
+    /// soon as work is shared or the last mutator stops.
     pub trace_wait_time: u64,
 
+    /// Decides when the arena is collected. Defaults to [`DefaultPolicy`],
+    /// which uses the monitor settings below.
+    pub collection_policy: Arc<dyn CollectionPolicy>,
     /// Once the amount of marked objects surpasses the max old object count
     /// a major collection will be triggered. The max old object count is calculated
     /// by multiplying this value by the amount of old objects marked in the
@@ -21,8 +60,12 @@ pub struct Config {
     /// Once the size of the arena divided by the previous arena size after collection
     /// surpassed this value, a minor collection will be triggered.
     pub monitor_arena_size_ratio_trigger: f32,
-    /// This setting this flag on or off will enable the monitor respectively.
+    /// The amount of miliseconds the monitor sleeps between checking whether
+    /// a collection should be triggered.
     pub monitor_wait_time: u64,
+    /// This setting this flag on or off will enable the monitor respectively.
+    /// The monitor may also be started and stopped once the arena has been
+    /// created, see [`crate::Arena::start_monitor`].
     pub monitor_on: bool,
 
     /// The minimum amount of work a mutator must accumulate before sending to
@@ -39,14 +82,65 @@ pub struct Config {
     /// that the tracers will outpace them. The timeslice size will effect the
     /// length of time the timeslicer will request a mutator to yield in a single
     /// time frame. This represent number represents milliseconds.
+    ///
+    /// During a trace, calls to [`crate::Mutator::gc_yield`] block for a
+    /// portion of each timeslice. That portion grows with the fraction of the
+    /// headroom which has been allocated since the trace began, up to the
+    /// entire timeslice, and shrinks as the trace nears completion.
     pub collector_timeslice_size: f64,
 
     /// The minimum amount of time the timeslicer will request yields for.
     /// Lowering this may help less aggressive mutators, but might make more
     /// aggressive mutators more likely to trigger yield earlier in the tracing
-    /// process (meaning less concurrency).
+    /// process (meaning less concurrency). Yields which would be shorter than
+    /// this are not requested at all.
     /// This represent number represents milliseconds.
     pub collector_slice_min: f64,
+
+    /// The approximate maximum size in bytes the arena may grow to, or `None`
+    /// for no limit. The size of the arena is estimated as its size after the
+    /// previous collection, plus the size of all allocations made since that
+    /// collection began tracing. A major collection is forced before a
+    /// mutation begins once the estimate exceeds the limit divided by
+    /// `1.0 + collector_max_headroom_ratio`.
+    pub max_heap_bytes: Option<u64>,
+    /// What happens when an allocation would exceed `max_heap_bytes`.
+    pub heap_limit_policy: HeapLimitPolicy,
+
+    /// When enabled, every major collection compacts the arena by moving the
+    /// live values out of sparsely occupied blocks into newly allocated space,
+    /// after which the blocks they previously occupied are freed. This
+    /// reduces fragmentation, at the cost of the major collection briefly
+    /// requiring extra memory and doing its compaction while all mutators are
+    /// stopped. Compaction is skipped while [`crate::Arena::view`] is active,
+    /// and abandoned if the extra memory cannot be allocated.
+    pub compaction_on: bool,
+
+    /// When enabled, the arena is swept by a background thread after each
+    /// collection, allowing mutators to resume as soon as tracing has
+    /// completed. Memory is then freed shortly after a collection returns,
+    /// rather than before, and mutators which allocate before the sweep has
+    /// finished will wait for it. Only has an effect with the `multi_threaded`
+    /// feature.
+    pub concurrent_sweep: bool,
+
+    /// When enabled, each collection checks that every value reachable from
+    /// the root and from [`crate::Handle`]s was marked by its trace,
+    /// panicking if one was not, which catches missing write barriers. It
+    /// then overwrites every value about to be freed with the byte `0xDE`, so
+    /// that a dangling [`crate::Gc`], such as one left by a hand-written
+    /// [`crate::Trace`] impl which skips a field, reads an obviously invalid
+    /// value, and the GC panics with "Bad GC Mark!" if it ever reaches it.
+    ///
+    /// Every allocation is recorded while enabled, making both allocation
+    /// and collection considerably slower. Intended for tests and debugging.
+    pub verify_heap: bool,
+
+    /// When set, collections are requested at the trigger points chosen by
+    /// the [`StressMode`], and run as soon as the mutation which requested
+    /// them exits, regardless of the [`Config::collection_policy`], and even
+    /// while the monitor is off. Intended for tests.
+    pub stress_mode: Option<StressMode>,
 }
 
 pub const GC_CONFIG_DEFAULT_TRACE_THREADS: usize = 2;
@@ -60,7 +154,7 @@ pub const GC_CONFIG_DEFAULT_TRACE_WAIT_TIME: u64 = 1;
 // at least makes it clear what values are "arbitrary"
 //
 // The Config can be updated after the Gc is created, but the update will only take place
-// until tracing has completed.
+// once any ongoing collection has completed.
 impl Config {
     /// Creates a default Config. Good for most use cases.
     pub fn default() -> Self {
@@ -71,6 +165,7 @@ impl Config {
             trace_share_ratio: GC_CONFIG_DEFAULT_TRACE_SHARE_RATIO,
             trace_wait_time: GC_CONFIG_DEFAULT_TRACE_WAIT_TIME,
 
+            collection_policy: Arc::new(DefaultPolicy),
             monitor_max_old_growth_rate: 1.5,
             monitor_arena_size_ratio_trigger: 1.5,
             monitor_wait_time: 10,
@@ -81,6 +176,462 @@ impl Config {
             collector_max_headroom_ratio: 0.5,
             collector_timeslice_size: 2.0,
             collector_slice_min: 0.6,
+
+            max_heap_bytes: None,
+            heap_limit_policy: HeapLimitPolicy::Fail,
+
+            compaction_on: false,
+
+            concurrent_sweep: false,
+
+            verify_heap: false,
+
+            stress_mode: None,
+        }
+    }
+    /// A Config which favors short pauses over throughput. Collections are
+    /// triggered earlier so that each trace has less to do, mutators are
+    /// given more headroom before the timeslicer paces them, and sweeping
+    /// happens in the background.
+    pub fn low_latency() -> Self {
+        Config {
+            monitor_arena_size_ratio_trigger: 1.25,
+            monitor_wait_time: 1,
+            mutator_share_min: 500,
+            collector_max_headroom_ratio: 1.0,
+            collector_timeslice_size: 1.0,
+            collector_slice_min: 0.2,
+            concurrent_sweep: true,
+            ..Config::default()
+        }
+    }
+
+    /// A Config which favors throughput over short pauses. The arena is
+    /// allowed to grow further between collections, and tracers work in
+    /// larger chunks, sharing work less often.
+    pub fn throughput() -> Self {
+        Config {
+            trace_chunk_size: 500,
+            trace_share_min: 200,
+            monitor_max_old_growth_rate: 2.0,
+            monitor_arena_size_ratio_trigger: 2.0,
+            monitor_wait_time: 50,
+            mutator_share_min: 5000,
+            ..Config::default()
         }
     }
+
+    /// A Config for machines with a single core, where additional tracers
+    /// and background sweeping would only compete with the mutators for
+    /// time. Mutators are paced sooner so that the lone tracer keeps up.
+    pub fn single_core() -> Self {
+        Config {
+            tracer_threads: 1,
+            collector_max_headroom_ratio: 0.25,
+            concurrent_sweep: false,
+            ..Config::default()
+        }
+    }
+
+    /// Creates a [`ConfigBuilder`] starting from the default Config.
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::from(Config::default())
+    }
+
+    /// Checks that each setting is within its allowed range.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.tracer_threads == 0 {
+            return Err(ConfigError::NoTracerThreads);
+        }
+
+        if self.trace_chunk_size == 0 {
+            return Err(ConfigError::EmptyTraceChunk);
+        }
+
+        if !(0.0..=1.0).contains(&self.trace_share_ratio) {
+            return Err(ConfigError::TraceShareRatioOutOfRange);
+        }
+
+        if !(1.0..).contains(&self.monitor_max_old_growth_rate) {
+            return Err(ConfigError::MaxOldGrowthRateTooSmall);
+        }
+
+        if !(1.0..).contains(&self.monitor_arena_size_ratio_trigger) {
+            return Err(ConfigError::ArenaSizeRatioTriggerTooSmall);
+        }
+
+        if !(0.0..).contains(&self.collector_max_headroom_ratio) {
+            return Err(ConfigError::NegativeHeadroom);
+        }
+
+        if self.collector_timeslice_size.is_nan()
+            || self.collector_timeslice_size <= 0.0
+            || !(0.0..=self.collector_timeslice_size).contains(&self.collector_slice_min)
+        {
+            return Err(ConfigError::InvalidTimeslice);
+        }
+
+        if let Some(StressMode {
+            trigger: StressTrigger::Random { one_in: 0, .. },
+            ..
+        }) = self.stress_mode
+        {
+            return Err(ConfigError::ZeroStressRate);
+        }
+
+        Ok(())
+    }
+
+    /// Creates a default Config with any overrides set in environment
+    /// variables applied, see [`Config::with_env_overrides`].
+    #[cfg(feature = "std")]
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Config::default().with_env_overrides()
+    }
+
+    /// Applies the overrides set in the following environment variables,
+    /// returning an error if any of them cannot be parsed, or if the
+    /// resulting Config is invalid.
+    ///
+    /// | Variable                           | Setting                                       |
+    /// |------------------------------------|-----------------------------------------------|
+    /// | `SANDPIT_TRACER_THREADS`           | [`Config::tracer_threads`]                    |
+    /// | `SANDPIT_TRACE_CHUNK_SIZE`         | [`Config::trace_chunk_size`]                  |
+    /// | `SANDPIT_TRACE_SHARE_MIN`          | [`Config::trace_share_min`]                   |
+    /// | `SANDPIT_TRACE_SHARE_RATIO`        | [`Config::trace_share_ratio`]                 |
+    /// | `SANDPIT_TRACE_WAIT_MS`            | [`Config::trace_wait_time`]                   |
+    /// | `SANDPIT_MAX_OLD_GROWTH_RATE`      | [`Config::monitor_max_old_growth_rate`]       |
+    /// | `SANDPIT_ARENA_SIZE_RATIO_TRIGGER` | [`Config::monitor_arena_size_ratio_trigger`]  |
+    /// | `SANDPIT_MONITOR_WAIT_MS`          | [`Config::monitor_wait_time`]                 |
+    /// | `SANDPIT_MONITOR_ON`               | [`Config::monitor_on`]                        |
+    /// | `SANDPIT_MUTATOR_SHARE_MIN`        | [`Config::mutator_share_min`]                 |
+    /// | `SANDPIT_MAX_HEADROOM_RATIO`       | [`Config::collector_max_headroom_ratio`]      |
+    /// | `SANDPIT_TIMESLICE_MS`             | [`Config::collector_timeslice_size`]          |
+    /// | `SANDPIT_SLICE_MIN_MS`             | [`Config::collector_slice_min`]               |
+    /// | `SANDPIT_MAX_HEAP`                 | [`Config::max_heap_bytes`], or `none`         |
+    /// | `SANDPIT_COMPACTION`               | [`Config::compaction_on`]                     |
+    /// | `SANDPIT_CONCURRENT_SWEEP`         | [`Config::concurrent_sweep`]                  |
+    /// | `SANDPIT_VERIFY_HEAP`              | [`Config::verify_heap`]                       |
+    ///
+    /// Flags accept `true`, `false`, `1` or `0`. Variables which are not set
+    /// leave their setting unchanged.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sandpit::{Arena, Config, Root};
+    ///
+    /// let config = Config::low_latency()
+    ///     .with_env_overrides()
+    ///     .expect("invalid SANDPIT_* environment variable");
+    ///
+    /// let arena: Arena<Root![()]> = Arena::new_with_config(config, |_| ());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn with_env_overrides(mut self) -> Result<Self, ConfigError> {
+        fn parse<T: core::str::FromStr>(value: &str) -> Option<T> {
+            value.parse().ok()
+        }
+
+        fn parse_flag(value: &str) -> Option<bool> {
+            match value {
+                "true" | "1" => Some(true),
+                "false" | "0" => Some(false),
+                _ => None,
+            }
+        }
+
+        fn parse_limit(value: &str) -> Option<Option<u64>> {
+            if value.eq_ignore_ascii_case("none") {
+                return Some(None);
+            }
+
+            value.parse().ok().map(Some)
+        }
+
+        env_override("SANDPIT_TRACER_THREADS", &mut self.tracer_threads, parse)?;
+        env_override("SANDPIT_TRACE_CHUNK_SIZE", &mut self.trace_chunk_size, parse)?;
+        env_override("SANDPIT_TRACE_SHARE_MIN", &mut self.trace_share_min, parse)?;
+        env_override("SANDPIT_TRACE_SHARE_RATIO", &mut self.trace_share_ratio, parse)?;
+        env_override("SANDPIT_TRACE_WAIT_MS", &mut self.trace_wait_time, parse)?;
+        env_override(
+            "SANDPIT_MAX_OLD_GROWTH_RATE",
+            &mut self.monitor_max_old_growth_rate,
+            parse,
+        )?;
+        env_override(
+            "SANDPIT_ARENA_SIZE_RATIO_TRIGGER",
+            &mut self.monitor_arena_size_ratio_trigger,
+            parse,
+        )?;
+        env_override("SANDPIT_MONITOR_WAIT_MS", &mut self.monitor_wait_time, parse)?;
+        env_override("SANDPIT_MONITOR_ON", &mut self.monitor_on, parse_flag)?;
+        env_override("SANDPIT_MUTATOR_SHARE_MIN", &mut self.mutator_share_min, parse)?;
+        env_override(
+            "SANDPIT_MAX_HEADROOM_RATIO",
+            &mut self.collector_max_headroom_ratio,
+            parse,
+        )?;
+        env_override("SANDPIT_TIMESLICE_MS", &mut self.collector_timeslice_size, parse)?;
+        env_override("SANDPIT_SLICE_MIN_MS", &mut self.collector_slice_min, parse)?;
+        env_override("SANDPIT_MAX_HEAP", &mut self.max_heap_bytes, parse_limit)?;
+        env_override("SANDPIT_COMPACTION", &mut self.compaction_on, parse_flag)?;
+        env_override("SANDPIT_CONCURRENT_SWEEP", &mut self.concurrent_sweep, parse_flag)?;
+        env_override("SANDPIT_VERIFY_HEAP", &mut self.verify_heap, parse_flag)?;
+
+        self.validate()?;
+
+        Ok(self)
+    }
+}
+
+// Overwrites `setting` with the parsed value of the environment variable, if
+// it is set.
+#[cfg(feature = "std")]
+fn env_override<T>(
+    var: &'static str,
+    setting: &mut T,
+    parse: fn(&str) -> Option<T>,
+) -> Result<(), ConfigError> {
+    let value = match std::env::var(var) {
+        Ok(value) => value,
+        Err(std::env::VarError::NotPresent) => return Ok(()),
+        Err(std::env::VarError::NotUnicode(_)) => return Err(ConfigError::InvalidEnvVar(var)),
+    };
+
+    *setting = parse(value.trim()).ok_or(ConfigError::InvalidEnvVar(var))?;
+
+    Ok(())
+}
+
+/// The error returned when a [`Config`] contains an invalid setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    /// [`Config::tracer_threads`] is zero.
+    NoTracerThreads,
+    /// [`Config::trace_chunk_size`] is zero.
+    EmptyTraceChunk,
+    /// [`Config::trace_share_ratio`] is not within `0.0..=1.0`.
+    TraceShareRatioOutOfRange,
+    /// [`Config::monitor_max_old_growth_rate`] is less than 1.0, which would
+    /// trigger a major collection on every check.
+    MaxOldGrowthRateTooSmall,
+    /// [`Config::monitor_arena_size_ratio_trigger`] is less than 1.0, which
+    /// would trigger a minor collection on every check.
+    ArenaSizeRatioTriggerTooSmall,
+    /// [`Config::collector_max_headroom_ratio`] is negative.
+    NegativeHeadroom,
+    /// [`Config::collector_timeslice_size`] is not positive, or
+    /// [`Config::collector_slice_min`] is not within `0.0..=collector_timeslice_size`.
+    InvalidTimeslice,
+    /// [`Config::stress_mode`] randomly collects at 1 in 0 trigger points.
+    ZeroStressRate,
+    /// The environment variable with the given name could not be parsed, see
+    /// [`Config::with_env_overrides`].
+    InvalidEnvVar(&'static str),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::NoTracerThreads => write!(f, "tracer_threads must be at least 1"),
+            ConfigError::EmptyTraceChunk => write!(f, "trace_chunk_size must be at least 1"),
+            ConfigError::TraceShareRatioOutOfRange => {
+                write!(f, "trace_share_ratio must be within 0.0..=1.0")
+            }
+            ConfigError::MaxOldGrowthRateTooSmall => {
+                write!(f, "monitor_max_old_growth_rate must be at least 1.0")
+            }
+            ConfigError::ArenaSizeRatioTriggerTooSmall => {
+                write!(f, "monitor_arena_size_ratio_trigger must be at least 1.0")
+            }
+            ConfigError::NegativeHeadroom => {
+                write!(f, "collector_max_headroom_ratio must not be negative")
+            }
+            ConfigError::InvalidTimeslice => write!(
+                f,
+                "collector_timeslice_size must be positive, and collector_slice_min within 0.0..=collector_timeslice_size"
+            ),
+            ConfigError::ZeroStressRate => write!(f, "stress_mode's one_in must be at least 1"),
+            ConfigError::InvalidEnvVar(var) => {
+                write!(f, "environment variable {} has an invalid value", var)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConfigError {}
+
+/// Builds a [`Config`], checking that its settings are valid.
+///
+/// See [`Config`] for a description of each setting.
+///
+/// # Example
+/// ```rust
+/// use sandpit::{Config, ConfigError};
+///
+/// let config = Config::builder()
+///     .tracer_threads(4)
+///     .concurrent_sweep(true)
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(config.tracer_threads, 4);
+///
+/// let invalid = Config::builder().trace_share_ratio(1.5).build();
+///
+/// assert_eq!(invalid.unwrap_err(), ConfigError::TraceShareRatioOutOfRange);
+/// ```
+///
+/// A builder may also start from one of the presets.
+///
+/// ```rust
+/// use sandpit::{Config, ConfigBuilder};
+///
+/// let config = ConfigBuilder::from(Config::low_latency())
+///     .tracer_threads(8)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl From<Config> for ConfigBuilder {
+    fn from(config: Config) -> Self {
+        Self { config }
+    }
+}
+
+impl ConfigBuilder {
+    /// Validates the settings, returning the Config if they are all valid.
+    pub fn build(self) -> Result<Config, ConfigError> {
+        self.config.validate()?;
+
+        Ok(self.config)
+    }
+
+    /// Sets [`Config::tracer_threads`].
+    pub fn tracer_threads(mut self, tracer_threads: usize) -> Self {
+        self.config.tracer_threads = tracer_threads;
+        self
+    }
+
+    /// Sets [`Config::trace_chunk_size`].
+    pub fn trace_chunk_size(mut self, trace_chunk_size: usize) -> Self {
+        self.config.trace_chunk_size = trace_chunk_size;
+        self
+    }
+
+    /// Sets [`Config::trace_share_min`].
+    pub fn trace_share_min(mut self, trace_share_min: usize) -> Self {
+        self.config.trace_share_min = trace_share_min;
+        self
+    }
+
+    /// Sets [`Config::trace_share_ratio`].
+    pub fn trace_share_ratio(mut self, trace_share_ratio: f32) -> Self {
+        self.config.trace_share_ratio = trace_share_ratio;
+        self
+    }
+
+    /// Sets [`Config::trace_wait_time`].
+    pub fn trace_wait_time(mut self, trace_wait_time: u64) -> Self {
+        self.config.trace_wait_time = trace_wait_time;
+        self
+    }
+
+    /// Sets [`Config::collection_policy`].
+    pub fn collection_policy(mut self, collection_policy: Arc<dyn CollectionPolicy>) -> Self {
+        self.config.collection_policy = collection_policy;
+        self
+    }
+
+    /// Sets [`Config::monitor_max_old_growth_rate`].
+    pub fn monitor_max_old_growth_rate(mut self, monitor_max_old_growth_rate: f32) -> Self {
+        self.config.monitor_max_old_growth_rate = monitor_max_old_growth_rate;
+        self
+    }
+
+    /// Sets [`Config::monitor_arena_size_ratio_trigger`].
+    pub fn monitor_arena_size_ratio_trigger(
+        mut self,
+        monitor_arena_size_ratio_trigger: f32,
+    ) -> Self {
+        self.config.monitor_arena_size_ratio_trigger = monitor_arena_size_ratio_trigger;
+        self
+    }
+
+    /// Sets [`Config::monitor_wait_time`].
+    pub fn monitor_wait_time(mut self, monitor_wait_time: u64) -> Self {
+        self.config.monitor_wait_time = monitor_wait_time;
+        self
+    }
+
+    /// Sets [`Config::monitor_on`].
+    pub fn monitor_on(mut self, monitor_on: bool) -> Self {
+        self.config.monitor_on = monitor_on;
+        self
+    }
+
+    /// Sets [`Config::mutator_share_min`].
+    pub fn mutator_share_min(mut self, mutator_share_min: usize) -> Self {
+        self.config.mutator_share_min = mutator_share_min;
+        self
+    }
+
+    /// Sets [`Config::collector_max_headroom_ratio`].
+    pub fn collector_max_headroom_ratio(mut self, collector_max_headroom_ratio: f64) -> Self {
+        self.config.collector_max_headroom_ratio = collector_max_headroom_ratio;
+        self
+    }
+
+    /// Sets [`Config::collector_timeslice_size`].
+    pub fn collector_timeslice_size(mut self, collector_timeslice_size: f64) -> Self {
+        self.config.collector_timeslice_size = collector_timeslice_size;
+        self
+    }
+
+    /// Sets [`Config::collector_slice_min`].
+    pub fn collector_slice_min(mut self, collector_slice_min: f64) -> Self {
+        self.config.collector_slice_min = collector_slice_min;
+        self
+    }
+
+    /// Sets [`Config::max_heap_bytes`].
+    pub fn max_heap_bytes(mut self, max_heap_bytes: Option<u64>) -> Self {
+        self.config.max_heap_bytes = max_heap_bytes;
+        self
+    }
+
+    /// Sets [`Config::heap_limit_policy`].
+    pub fn heap_limit_policy(mut self, heap_limit_policy: HeapLimitPolicy) -> Self {
+        self.config.heap_limit_policy = heap_limit_policy;
+        self
+    }
+
+    /// Sets [`Config::compaction_on`].
+    pub fn compaction_on(mut self, compaction_on: bool) -> Self {
+        self.config.compaction_on = compaction_on;
+        self
+    }
+
+    /// Sets [`Config::concurrent_sweep`].
+    pub fn concurrent_sweep(mut self, concurrent_sweep: bool) -> Self {
+        self.config.concurrent_sweep = concurrent_sweep;
+        self
+    }
+
+    /// Sets [`Config::verify_heap`].
+    pub fn verify_heap(mut self, verify_heap: bool) -> Self {
+        self.config.verify_heap = verify_heap;
+        self
+    }
+
+    /// Sets [`Config::stress_mode`].
+    pub fn stress_mode(mut self, stress_mode: Option<StressMode>) -> Self {
+        self.config.stress_mode = stress_mode;
+        self
+    }
 }
@@ -0,0 +1,117 @@
+use super::gc::{Gc, GcOpt, GcWeak};
+use super::header::{GcHeader, GcMark};
+use super::trace::{Forwarding, Trace, Tracer, WeakRef};
+
+/// A key/value pair where the value is only kept alive if the key is.
+///
+/// An ephemeron holds its key weakly, like a [`GcWeak`], and holds its value
+/// strongly, but only for as long as the key is reachable from elsewhere.
+/// The tracers will only trace the value once they have proven the key to be
+/// live. If the key is never marked, the GC clears both the key and the value.
+///
+/// Ephemerons are useful for attaching side-table data to arena values without
+/// keeping those values alive. Note that a value referencing its own key does
+/// not keep the key alive.
+///
+/// # Example
+/// ```rust
+/// use sandpit::{Arena, Ephemeron, Gc, GcOpt, Root, Trace};
+///
+/// #[derive(Trace)]
+/// struct Table<'gc> {
+///     key: GcOpt<'gc, usize>,
+///     entry: Ephemeron<'gc, usize, str>,
+/// }
+///
+/// let arena: Arena<Root![Table<'_>]> = Arena::new(|mu| {
+///     let key = Gc::new(mu, 1);
+///     let value = mu.alloc_str("debug info for 1");
+///
+///     Table {
+///         entry: Ephemeron::new(key.clone(), value),
+///         key: key.into(),
+///     }
+/// });
+///
+/// // the key is reachable, so the value is kept alive
+/// arena.major_collect();
+/// arena.mutate(|_, root| assert_eq!(&*root.entry.value().unwrap(), "debug info for 1"));
+///
+/// // once the key is unreachable the entry is cleared
+/// arena.mutate(|_, root| root.key.set_none());
+/// arena.major_collect();
+/// arena.mutate(|_, root| assert!(root.entry.get().is_none()));
+/// ```
+pub struct Ephemeron<'gc, K: Trace + ?Sized, V: Trace + ?Sized> {
+    key: GcWeak<'gc, K>,
+    value: GcOpt<'gc, V>,
+}
+
+impl<'gc, K: Trace + ?Sized, V: Trace + ?Sized> Ephemeron<'gc, K, V> {
+    /// Create a new ephemeron which keeps `value` alive as long as `key` is
+    /// reachable.
+    pub fn new(key: Gc<'gc, K>, value: Gc<'gc, V>) -> Self {
+        Self {
+            key: key.downgrade(),
+            value: value.into(),
+        }
+    }
+
+    /// Returns the key, or `None` if the ephemeron has been cleared.
+    pub fn key(&self) -> Option<Gc<'gc, K>> {
+        self.key.upgrade()
+    }
+
+    /// Returns the value, or `None` if the ephemeron has been cleared.
+    pub fn value(&self) -> Option<Gc<'gc, V>> {
+        self.value.as_option()
+    }
+
+    /// Returns both the key and value, or `None` if the ephemeron has been cleared.
+    pub fn get(&self) -> Option<(Gc<'gc, K>, Gc<'gc, V>)> {
+        Some((self.key()?, self.value()?))
+    }
+
+    /// Check whether the ephemeron has been cleared.
+    pub fn is_none(&self) -> bool {
+        self.key.is_none()
+    }
+
+    pub(crate) fn trace_value(&self, tracer: &mut Tracer) {
+        self.value.trace(tracer);
+    }
+
+    pub(crate) fn trace_value_if_key_marked(&self, tracer: &mut Tracer) -> bool {
+        match self.key.upgrade() {
+            Some(key) if key.get_header().get_mark() != tracer.get_mark() => false,
+            Some(_) => {
+                self.value.trace(tracer);
+                true
+            }
+            None => true,
+        }
+    }
+}
+
+// Called by the collector once a trace has completed, and before sweeping.
+impl<'gc, K: Trace + ?Sized, V: Trace + ?Sized> WeakRef for Ephemeron<'gc, K, V> {
+    fn clear_if_unmarked(&self, mark: GcMark) {
+        self.key.clear_if_unmarked(mark);
+
+        if self.key.is_none() {
+            self.value.set_none();
+        }
+    }
+
+    fn forward(&self, forwarding: &Forwarding) {
+        self.key.forward(forwarding);
+    }
+}
+
+unsafe impl<'gc, K: Trace + ?Sized, V: Trace + ?Sized> Trace for Ephemeron<'gc, K, V> {
+    const IS_LEAF: bool = false;
+
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.trace_ephemeron(self);
+    }
+}
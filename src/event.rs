@@ -0,0 +1,135 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+#[cfg(not(feature = "multi_threaded"))]
+use core::cell::RefCell;
+#[cfg(feature = "multi_threaded")]
+use std::sync::RwLock;
+
+/// The kind of a collection reported by a [`GcEvent`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CollectionKind {
+    Major,
+    Minor,
+}
+
+/// An event emitted by the GC to each [`GcEventListener`] registered with
+/// [`crate::Arena::add_event_listener`].
+///
+/// Sizes are in bytes, and are the total size of the arena at that point.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GcEvent {
+    /// A collection has acquired the collection lock and is about to begin
+    /// tracing.
+    CollectionStarted {
+        kind: CollectionKind,
+        arena_size: u64,
+    },
+    /// A collection has completed. With [`crate::Config::concurrent_sweep`]
+    /// the sweep may still be running, in which case `bytes_after` does not
+    /// yet reflect the memory it frees.
+    CollectionFinished {
+        kind: CollectionKind,
+        duration: Duration,
+        bytes_before: u64,
+        bytes_after: u64,
+        objects_marked: u64,
+    },
+    /// Mutators have been asked to yield, either b/c they have allocated
+    /// beyond the collector's headroom, or b/c a major collection was
+    /// requested. Only emitted with the `multi_threaded` feature.
+    YieldRequested,
+    /// The tracers have run out of work and are waiting on the remaining
+    /// mutators to yield before the trace can complete. Only emitted with the
+    /// `multi_threaded` feature.
+    WaitingOnMutators { active_mutators: usize },
+    /// The arena has been swept, freeing the memory of unmarked values.
+    SweepFinished { duration: Duration, arena_size: u64 },
+}
+
+/// Receives the events emitted by an arena's GC.
+///
+/// Listeners are called synchronously from whichever thread emitted the
+/// event, which may be a tracer, sweeper or monitor thread, as well as from
+/// within a collection. They should return quickly, and must not
+/// collect or mutate the arena which emitted the event.
+///
+/// Any `Fn(&GcEvent)` closure which is `Send + Sync` is a listener.
+///
+/// # Example
+/// ```rust
+/// use sandpit::{Arena, GcEvent, Root};
+/// use std::sync::Arc;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// let arena: Arena<Root![()]> = Arena::new(|_| ());
+/// let collections = Arc::new(AtomicUsize::new(0));
+/// let counter = collections.clone();
+///
+/// arena.add_event_listener(Arc::new(move |event: &GcEvent| {
+///     if let GcEvent::CollectionFinished { .. } = event {
+///         counter.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }));
+///
+/// arena.major_collect();
+///
+/// assert_eq!(collections.load(Ordering::Relaxed), 1);
+/// ```
+pub trait GcEventListener: Send + Sync {
+    fn on_event(&self, event: &GcEvent);
+}
+
+impl<F: Fn(&GcEvent) + Send + Sync> GcEventListener for F {
+    fn on_event(&self, event: &GcEvent) {
+        self(event)
+    }
+}
+
+// The listeners registered with an arena, shared by its collector and
+// sweeper.
+pub struct EventListeners {
+    #[cfg(feature = "multi_threaded")]
+    listeners: RwLock<Vec<Arc<dyn GcEventListener>>>,
+    #[cfg(not(feature = "multi_threaded"))]
+    listeners: RefCell<Vec<Arc<dyn GcEventListener>>>,
+}
+
+impl EventListeners {
+    pub fn new() -> Self {
+        Self {
+            listeners: Default::default(),
+        }
+    }
+
+    #[cfg(feature = "multi_threaded")]
+    pub fn add(&self, listener: Arc<dyn GcEventListener>) {
+        self.listeners.write().unwrap().push(listener);
+    }
+
+    #[cfg(not(feature = "multi_threaded"))]
+    pub fn add(&self, listener: Arc<dyn GcEventListener>) {
+        self.listeners.borrow_mut().push(listener);
+    }
+
+    // The listeners are cloned out so that none of them are called while the
+    // list is borrowed, allowing a listener to register another.
+    #[cfg(feature = "multi_threaded")]
+    pub fn emit(&self, event: GcEvent) {
+        let listeners = self.listeners.read().unwrap().clone();
+
+        for listener in listeners {
+            listener.on_event(&event);
+        }
+    }
+
+    #[cfg(not(feature = "multi_threaded"))]
+    pub fn emit(&self, event: GcEvent) {
+        let listeners = self.listeners.borrow().clone();
+
+        for listener in listeners {
+            listener.on_event(&event);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use super::gc::Gc;
+use super::header::{GcHeader, GcMark};
+use super::pointee::Thin;
+use super::trace::{Forwarding, Trace};
+use core::ptr::NonNull;
+
+/// Allows a GC value to run cleanup code once it has been found unreachable.
+///
+/// Types implementing [`Trace`] may not impl Drop, so by default the GC simply
+/// reuses the memory of unreachable values without running any code. Values
+/// allocated with [`crate::Mutator::alloc_finalized`] are instead recorded by
+/// the collector, and after a trace determines they are unreachable (but before
+/// their memory is swept) `finalize` is called, after which the value is
+/// dropped in place. Any values still awaiting finalization when the arena is
+/// dropped are finalized at that point.
+///
+/// Finalizers run during a collection while no mutation is taking place, or
+/// while the arena is being dropped.
+///
+/// ## Safety
+/// When a finalizer runs, any GC values referenced by `self` may already have
+/// been finalized, or even freed. Implementors must not dereference any GC
+/// pointers held by `self` within `finalize`.
+///
+/// ## Example
+/// ```rust
+/// use sandpit::{Arena, Finalize, Gc, Root, Trace};
+///
+/// #[derive(Trace)]
+/// struct FileHandle {
+///     fd: usize,
+/// }
+///
+/// unsafe impl Finalize for FileHandle {
+///     fn finalize(&mut self) {
+///         // release the OS resource here
+///     }
+/// }
+///
+/// let arena: Arena<Root![()]> = Arena::new(|_| ());
+///
+/// arena.mutate(|mu, _| {
+///     let handle: Gc<FileHandle> = mu.alloc_finalized(FileHandle { fd: 3 });
+/// });
+///
+/// // the handle is unreachable, so it will be finalized by this collection
+/// arena.major_collect();
+/// ```
+pub unsafe trait Finalize: Trace {
+    /// Called once after the value has been found unreachable.
+    fn finalize(&mut self);
+}
+
+unsafe impl Send for FinalizeJob {}
+unsafe impl Sync for FinalizeJob {}
+
+// A type erased record of an allocation which needs to be finalized once it
+// is no longer marked.
+pub struct FinalizeJob {
+    ptr: NonNull<Thin<()>>,
+    dyn_get_mark: fn(NonNull<Thin<()>>) -> GcMark,
+    dyn_finalize: unsafe fn(NonNull<Thin<()>>),
+}
+
+impl FinalizeJob {
+    pub fn new<T: Finalize>(ptr: NonNull<Thin<T>>) -> Self {
+        Self {
+            ptr: ptr.cast(),
+            dyn_get_mark: get_mark::<T>,
+            dyn_finalize: finalize::<T>,
+        }
+    }
+
+    pub fn forward(&mut self, forwarding: &Forwarding) {
+        if let Some(new) = forwarding.get(self.ptr.as_ptr() as usize) {
+            self.ptr = new;
+        }
+    }
+
+    pub fn is_marked(&self, mark: GcMark) -> bool {
+        (self.dyn_get_mark)(self.ptr) == mark
+    }
+
+    // SAFETY: the value must be unreachable and not yet swept, and the job
+    // must never be run again.
+    pub unsafe fn finalize(self) {
+        (self.dyn_finalize)(self.ptr)
+    }
+}
+
+fn get_mark<T: Finalize>(ptr: NonNull<Thin<()>>) -> GcMark {
+    let gc: Gc<'_, T> = unsafe { Gc::from_ptr(ptr.cast::<T>().as_ptr()) };
+
+    gc.get_header().get_mark()
+}
+
+unsafe fn finalize<T: Finalize>(ptr: NonNull<Thin<()>>) {
+    let value: *mut T = ptr.cast().as_ptr();
+
+    (*value).finalize();
+    core::ptr::drop_in_place(value);
+}
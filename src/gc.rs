@@ -4,13 +4,16 @@
 //! * An object may only be referenced by a GC pointer if it implements [`crate::Trace`].
 //! * Any object capable of containing a GC pointer may not impl [`crate::TraceLeaf`].
 //!
-//! [`Gc`] and [`GcOpt`] may be updated via a [`crate::WriteBarrier`] to point
-//! at different values.
+//! [`Gc`], [`GcOpt`] and [`GcWeak`] may be updated via a [`crate::WriteBarrier`]
+//! to point at different values.
+//!
+//! A [`GcWeak`] does not keep the value it points at alive.
 use super::trace::Trace;
 use crate::barrier::WriteBarrier;
-use crate::header::GcHeader;
+use crate::header::{GcHeader, GcMark};
 use crate::mutator::Mutator;
 use crate::pointee::{GcPointee, Thin};
+use crate::trace::{Forwarding, Tracer, WeakRef};
 
 use alloc::alloc::Layout;
 use core::marker::PhantomData;
@@ -119,10 +122,22 @@ impl<'gc, T: Trace + ?Sized> Gc<'gc, T> {
         }
     }
 
+    // SAFETY: same as `from_ptr`
+    pub(crate) unsafe fn from_thin(ptr: NonNull<Thin<T>>) -> Self {
+        Self {
+            ptr: AtomicPtr::new(ptr.as_ptr()),
+            scope: PhantomData::<&'gc *mut T>,
+        }
+    }
+
     pub(crate) fn get_header(&self) -> &<T as GcPointee>::GcHeader {
         <T as GcPointee>::get_header(self.as_thin())
     }
 
+    pub(crate) fn evacuate(&self, tracer: &mut Tracer) {
+        tracer.evacuate(&self.ptr);
+    }
+
     // HACK: THIS EXIST FOR PROVENANCE
     pub(crate) fn get_header_ptr(&self) -> *const <T as GcPointee>::GcHeader {
         <T as GcPointee>::get_header_ptr(self.as_thin())
@@ -322,6 +337,14 @@ impl<'gc, T: Trace + ?Sized> GcOpt<'gc, T> {
         self.ptr.store(null_mut(), Ordering::Relaxed)
     }
 
+    pub(crate) fn evacuate(&self, tracer: &mut Tracer) {
+        tracer.evacuate(&self.ptr);
+    }
+
+    pub(crate) fn evacuate_untraced(&self, tracer: &mut Tracer) {
+        tracer.evacuate_untraced(&self.ptr);
+    }
+
     /// Convert into a Option of [`Gc`].
     ///
     /// # Example
@@ -379,6 +402,153 @@ impl<'gc, T: Trace + ?Sized> GcOpt<'gc, T> {
     }
 }
 
+/// A weak GC pointer which does not keep the value it points at alive.
+///
+/// The tracers do not mark through a [`GcWeak`], so if the only remaining
+/// references to a value are weak, the value will be freed by the next
+/// collection. When that happens, the collector clears every [`GcWeak`]
+/// still pointing at it, causing [`GcWeak::upgrade`] to return `None`.
+///
+/// A [`GcWeak`] can be updated via a [`crate::barrier::WriteBarrier`] just like
+/// a [`GcOpt`].
+///
+/// # Example
+/// ```rust
+/// use sandpit::{Arena, Gc, GcWeak, Root};
+///
+/// let arena: Arena<Root![GcWeak<'_, usize>]> = Arena::new(|mu| {
+///    Gc::new(mu, 123).downgrade()
+/// });
+///
+/// // nothing strongly references the value, so it will be freed
+/// arena.major_collect();
+///
+/// arena.mutate(|mu, root| {
+///     assert!(root.upgrade().is_none());
+/// });
+/// ```
+pub struct GcWeak<'gc, T: Trace + ?Sized> {
+    ptr: AtomicPtr<Thin<T>>,
+    scope: PhantomData<&'gc *mut T>,
+}
+
+impl<'gc, T: Trace + ?Sized> From<Gc<'gc, T>> for GcWeak<'gc, T> {
+    fn from(gc: Gc<'gc, T>) -> Self {
+        Self {
+            ptr: AtomicPtr::new(gc.ptr.load(Ordering::Relaxed)),
+            scope: PhantomData::<&'gc *mut T>,
+        }
+    }
+}
+
+impl<'gc, T: Trace + ?Sized> Clone for GcWeak<'gc, T> {
+    fn clone(&self) -> Self {
+        Self {
+            ptr: AtomicPtr::new(self.ptr.load(Ordering::Relaxed)),
+            scope: PhantomData::<&'gc *mut T>,
+        }
+    }
+}
+
+impl<'gc, T: Trace + ?Sized> Gc<'gc, T> {
+    /// Create a [`GcWeak`] pointing at the same value as this [`Gc`].
+    ///
+    /// # Example
+    /// ```rust
+    /// # use sandpit::{Arena, Gc, Root};
+    /// # let arena: Arena<Root![()]> = Arena::new(|mu| {
+    ///    let gc = Gc::new(mu, 123);
+    ///    let weak = gc.downgrade();
+    ///
+    ///    assert!(*weak.upgrade().unwrap() == 123);
+    /// # });
+    ///```
+    pub fn downgrade(&self) -> GcWeak<'gc, T> {
+        GcWeak::from(self.clone())
+    }
+}
+
+impl<'gc, T: Trace + ?Sized> GcWeak<'gc, T> {
+    /// Creates a new [`GcWeak`] which points to nothing.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sandpit::{Arena, GcWeak, Root};
+    ///
+    /// let arena: Arena<Root![GcWeak<'_, usize>]> = Arena::new(|mu| {
+    ///    GcWeak::new_none()
+    /// });
+    ///```
+    pub fn new_none() -> Self {
+        Self {
+            ptr: AtomicPtr::new(null_mut()),
+            scope: PhantomData::<&'gc *mut T>,
+        }
+    }
+
+    /// Attempt to obtain a strong [`Gc`] to the pointed at value.
+    ///
+    /// Returns `None` if the value has been freed by the GC, or if this
+    /// [`GcWeak`] was created via [`GcWeak::new_none`].
+    ///
+    /// # Example
+    /// ```rust
+    /// # use sandpit::{Arena, Gc, Root};
+    /// # let arena: Arena<Root![()]> = Arena::new(|mu| {
+    ///    let weak = Gc::new(mu, 123).downgrade();
+    ///
+    ///    assert!(*weak.upgrade().unwrap() == 123);
+    /// # });
+    ///```
+    pub fn upgrade(&self) -> Option<Gc<'gc, T>> {
+        let ptr = self.ptr.load(Ordering::Relaxed);
+
+        if ptr.is_null() {
+            None
+        } else {
+            Some(Gc {
+                ptr: AtomicPtr::new(ptr),
+                scope: PhantomData::<&'gc *mut T>,
+            })
+        }
+    }
+
+    /// Check whether this [`GcWeak`] no longer points at a value.
+    pub fn is_none(&self) -> bool {
+        self.ptr.load(Ordering::Relaxed).is_null()
+    }
+
+    /// Check whether this [`GcWeak`] still points at a value.
+    pub fn is_some(&self) -> bool {
+        !self.is_none()
+    }
+
+    // If the tracers have already traced this pointer, than the new pointer
+    // must be retraced before the end of the mutation context.
+    //
+    // Use a write barrier to call this method safely.
+    pub(crate) unsafe fn set(&self, new: GcWeak<'gc, T>) {
+        let thin_ptr = new.ptr.load(Ordering::Relaxed);
+
+        self.ptr.store(thin_ptr, Ordering::SeqCst);
+    }
+}
+
+// Called by the collector once a trace has completed, and before sweeping.
+impl<'gc, T: Trace + ?Sized> WeakRef for GcWeak<'gc, T> {
+    fn clear_if_unmarked(&self, mark: GcMark) {
+        if let Some(gc) = self.upgrade() {
+            if gc.get_header().get_mark() != mark {
+                self.ptr.store(null_mut(), Ordering::SeqCst);
+            }
+        }
+    }
+
+    fn forward(&self, forwarding: &Forwarding) {
+        forwarding.forward(&self.ptr);
+    }
+}
+
 /*
 pub trait GcPointer: Trace + Clone {
     const POINTEE_ALIGNMENT: usize;
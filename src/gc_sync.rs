@@ -1,4 +1,4 @@
-use super::gc::{Gc, GcOpt};
+use super::gc::{Gc, GcOpt, GcWeak};
 use super::mutator::Mutator;
 use super::tagged::{Tag, Tagged};
 use super::trace::{Trace, TraceLeaf};
@@ -37,6 +37,12 @@ impl<'gc, T: Trace + ?Sized> GcSync<'gc> for GcOpt<'gc, T> {
     }
 }
 
+impl<'gc, T: Trace + ?Sized> GcSync<'gc> for GcWeak<'gc, T> {
+    unsafe fn gc_swap(old: &Self, new: Self, _mu: &'gc Mutator) {
+        old.set(new);
+    }
+}
+
 impl<'gc, B: Tag + 'gc> GcSync<'gc> for Tagged<'gc, B> {
     unsafe fn gc_swap(old: &Self, new: Self, _mu: &'gc Mutator) {
         old.set(new.get_raw());
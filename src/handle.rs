@@ -0,0 +1,167 @@
+use super::gc::Gc;
+use super::mutator::Mutator;
+use super::pointee::Thin;
+use super::trace::{Collector, Forwarding, Trace, TraceJob};
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use higher_kinded_types::ForLt;
+use std::sync::Mutex;
+
+/// A persistent root to a value within an arena, which may be held outside of
+/// a mutation context.
+///
+/// A `Gc` cannot escape the mutation it was obtained in, meaning normally
+/// everything that should survive a collection must be reachable from the
+/// arena's root. A [`Handle`] instead registers its value in a set of extra
+/// roots owned by the arena, which the tracers mark on every collection for as
+/// long as the handle exists. Dropping the handle removes the root.
+///
+/// Handles are created via [`crate::Mutator::root_handle`] and can be turned
+/// back into a `Gc` within a later mutation via [`Handle::get`].
+///
+/// Like the root of an arena, the type of a handle's value must be a Higher
+/// Kinded Type(HKT), so that the value's `'gc` lifetime can be re-branded when
+/// it is obtained within a different mutation.
+///
+/// # Example
+/// ```rust
+/// use sandpit::{Arena, Gc, Handle, Root};
+///
+/// let arena: Arena<Root![()]> = Arena::new(|_| ());
+///
+/// let handle: Handle<Root![Gc<'_, usize>]> = arena.mutate(|mu, _| {
+///     let callback = Gc::new(mu, Gc::new(mu, 42));
+///
+///     mu.root_handle(&callback)
+/// });
+///
+/// // the value is only reachable through the handle, but is not freed
+/// arena.major_collect();
+///
+/// arena.mutate(|mu, _| {
+///     let callback = handle.get(mu);
+///
+///     assert_eq!(**callback, 42);
+/// });
+/// ```
+pub struct Handle<R: ForLt + 'static>
+where
+    for<'a> <R as ForLt>::Of<'a>: Trace,
+{
+    id: usize,
+    roots: Arc<RootSet>,
+    _type: PhantomData<*const R::Of<'static>>,
+}
+
+impl<R: ForLt + 'static> Handle<R>
+where
+    for<'a> <R as ForLt>::Of<'a>: Trace,
+{
+    pub(crate) fn new<'gc>(collector: &Collector, gc: &Gc<'gc, R::Of<'gc>>) -> Self {
+        let job = TraceJob::new_root::<R::Of<'gc>>(gc.as_thin());
+        let roots = collector.root_set().clone();
+        let id = roots.insert(job.clone());
+
+        // A trace may currently be underway which has already traced the
+        // root set, so the value must be traced as well.
+        collector.send_work(alloc::vec![job]);
+
+        Self {
+            id,
+            roots,
+            _type: PhantomData::<*const R::Of<'static>>,
+        }
+    }
+
+    /// Obtain a `Gc` to the handle's value within a mutation context.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the mutator does not belong to the arena the handle was created in.
+    pub fn get<'gc>(&self, mu: &'gc Mutator<'gc>) -> Gc<'gc, R::Of<'gc>> {
+        assert!(
+            Arc::ptr_eq(&self.roots, mu.root_set()),
+            "handle used with a mutator from a different arena"
+        );
+
+        // SAFETY: the value is rooted by this handle, so it has not been freed
+        unsafe { Gc::from_thin(self.roots.get(self.id).cast()) }
+    }
+}
+
+impl<R: ForLt + 'static> Clone for Handle<R>
+where
+    for<'a> <R as ForLt>::Of<'a>: Trace,
+{
+    fn clone(&self) -> Self {
+        let job = self.roots.get_job(self.id);
+
+        Self {
+            id: self.roots.insert(job),
+            roots: self.roots.clone(),
+            _type: PhantomData::<*const R::Of<'static>>,
+        }
+    }
+}
+
+impl<R: ForLt + 'static> Drop for Handle<R>
+where
+    for<'a> <R as ForLt>::Of<'a>: Trace,
+{
+    fn drop(&mut self) {
+        self.roots.remove(self.id);
+    }
+}
+
+unsafe impl Send for RootSet {}
+unsafe impl Sync for RootSet {}
+
+// The set of extra roots registered by handles, which are traced alongside
+// the arena's root.
+pub struct RootSet {
+    next_id: AtomicUsize,
+    roots: Mutex<BTreeMap<usize, TraceJob>>,
+}
+
+impl RootSet {
+    pub fn new() -> Self {
+        Self {
+            next_id: AtomicUsize::new(0),
+            roots: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn insert(&self, job: TraceJob) -> usize {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+        self.roots.lock().unwrap().insert(id, job);
+
+        id
+    }
+
+    fn remove(&self, id: usize) {
+        self.roots.lock().unwrap().remove(&id);
+    }
+
+    fn get_job(&self, id: usize) -> TraceJob {
+        self.roots.lock().unwrap()[&id].clone()
+    }
+
+    fn get(&self, id: usize) -> NonNull<Thin<()>> {
+        self.get_job(id).as_ptr()
+    }
+
+    pub fn forward(&self, forwarding: &Forwarding) {
+        for job in self.roots.lock().unwrap().values_mut() {
+            job.forward(forwarding);
+        }
+    }
+
+    pub fn jobs(&self) -> Vec<TraceJob> {
+        self.roots.lock().unwrap().values().cloned().collect()
+    }
+}
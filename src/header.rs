@@ -63,6 +63,9 @@ impl From<GcMark> for NonZero<u8> {
     }
 }
 
+// Every header begins with its mark, which lets heap verification check the
+// mark of an allocation without knowing its type.
+#[repr(C)]
 pub struct SizedHeader<T> {
     mark: AtomicU8,
     _item_type: PhantomData<T>,
@@ -94,6 +97,7 @@ impl<T> GcHeader for SizedHeader<T> {
 }
 
 // for dynamically sized types
+#[repr(C)]
 pub struct SliceHeader<T> {
     mark: AtomicU8,
     len: usize,
@@ -130,6 +134,7 @@ impl<T> GcHeader for SliceHeader<T> {
     }
 }
 
+#[repr(C)]
 pub struct StrHeader {
     mark: AtomicU8,
     len: usize,
@@ -1,11 +1,80 @@
 use super::header::GcMark;
 use alloc::alloc::Layout;
-use nimix::{mark as nimix_mark, Allocator as NimixAllocator, Heap as NimixHeap};
+#[cfg(feature = "multi_threaded")]
+use alloc::sync::Arc;
+use core::fmt;
+#[cfg(feature = "multi_threaded")]
+use core::mem::ManuallyDrop;
+use nimix::{
+    mark as nimix_mark, AllocError as NimixAllocError, Allocator as NimixAllocator,
+    Heap as NimixHeap,
+};
+#[cfg(feature = "multi_threaded")]
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+#[cfg(feature = "multi_threaded")]
+use std::sync::{RwLock, RwLockWriteGuard};
 
+/// The error returned by the fallible allocation methods of a
+/// [`crate::Mutator`], such as [`crate::Mutator::try_alloc`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocError {
+    /// The allocator was unable to obtain more memory.
+    OutOfMemory,
+    /// The requested size is too large to be allocated.
+    TooLarge,
+    /// The allocation would grow the arena beyond [`crate::Config::max_heap_bytes`].
+    HeapLimitExceeded,
+}
+
+impl From<NimixAllocError> for AllocError {
+    fn from(value: NimixAllocError) -> Self {
+        match value {
+            NimixAllocError::OOM => AllocError::OutOfMemory,
+            NimixAllocError::AllocOverflow | NimixAllocError::LayoutError => {
+                AllocError::TooLarge
+            }
+        }
+    }
+}
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AllocError::OutOfMemory => write!(f, "out of memory"),
+            AllocError::TooLarge => write!(f, "allocation too large"),
+            AllocError::HeapLimitExceeded => write!(f, "heap limit exceeded"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AllocError {}
+
+#[cfg(not(feature = "multi_threaded"))]
 pub struct Allocator {
     allocator: NimixAllocator,
 }
 
+// The block lists shared by every allocator are not safe to access while
+// they are being swept, so allocators hold the heap's block lock whenever
+// they may take or return blocks during a sweep.
+#[cfg(feature = "multi_threaded")]
+pub struct Allocator {
+    allocator: ManuallyDrop<NimixAllocator>,
+    blocks: Arc<BlockLock>,
+}
+
+// Sweeps are flagged before the lock is taken, and only unflagged once it has
+// been released, so allocators which see no sweep in flight need not take the
+// lock. Mutators are stopped when a sweep begins, so none can be allocating
+// without the lock when it is taken.
+#[cfg(feature = "multi_threaded")]
+struct BlockLock {
+    lock: RwLock<()>,
+    sweeping: AtomicBool,
+}
+
+#[cfg(not(feature = "multi_threaded"))]
 impl From<&Heap> for Allocator {
     fn from(value: &Heap) -> Self {
         Allocator {
@@ -14,30 +83,125 @@ impl From<&Heap> for Allocator {
     }
 }
 
+#[cfg(feature = "multi_threaded")]
+impl From<&Heap> for Allocator {
+    fn from(value: &Heap) -> Self {
+        Allocator {
+            allocator: ManuallyDrop::new(NimixAllocator::from(&value.heap)),
+            blocks: value.blocks.clone(),
+        }
+    }
+}
+
 impl Allocator {
-    pub fn alloc(&self, layout: Layout) -> *const u8 {
-        unsafe { self.allocator.alloc(layout).expect("Failed to allocate") }
+    pub fn try_alloc(&self, layout: Layout) -> Result<*const u8, AllocError> {
+        #[cfg(feature = "multi_threaded")]
+        let _guard = self
+            .blocks
+            .sweeping
+            .load(Ordering::Acquire)
+            .then(|| self.blocks.lock.read().unwrap());
+
+        unsafe { Ok(self.allocator.alloc(layout)?) }
+    }
+}
+
+// Allocators may be dropped after their mutator has stopped, so they always
+// take the lock to return their blocks.
+#[cfg(feature = "multi_threaded")]
+impl Drop for Allocator {
+    fn drop(&mut self) {
+        let _guard = self.blocks.lock.read().unwrap();
+
+        // SAFETY: the allocator is not used again. Dropping it returns its
+        // blocks to the heap.
+        unsafe { ManuallyDrop::drop(&mut self.allocator) }
     }
 }
 
 pub struct Heap {
     heap: NimixHeap,
+    #[cfg(feature = "multi_threaded")]
+    blocks: Arc<BlockLock>,
+    // the size of the heap when it was last measured
+    #[cfg(feature = "multi_threaded")]
+    size: AtomicU64,
 }
 
 impl Heap {
     pub fn new() -> Self {
         Self {
             heap: NimixHeap::new(),
+            #[cfg(feature = "multi_threaded")]
+            blocks: Arc::new(BlockLock {
+                lock: RwLock::new(()),
+                sweeping: AtomicBool::new(false),
+            }),
+            #[cfg(feature = "multi_threaded")]
+            size: AtomicU64::new(0),
         }
     }
 
+    #[cfg(not(feature = "multi_threaded"))]
+    pub unsafe fn sweep(&self, live_mark: GcMark) {
+        self.heap.sweep(live_mark.into())
+    }
+
+    #[cfg(feature = "multi_threaded")]
     pub unsafe fn sweep(&self, live_mark: GcMark) {
+        let guard = self.lock_blocks();
+
+        self.sweep_locked(live_mark, &guard)
+    }
+
+    // Prevents allocators from taking or returning blocks until the guard is
+    // dropped.
+    #[cfg(feature = "multi_threaded")]
+    pub fn lock_blocks(&self) -> BlockGuard<'_> {
+        self.blocks.sweeping.store(true, Ordering::SeqCst);
+
+        BlockGuard {
+            guard: Some(self.blocks.lock.write().unwrap()),
+            sweeping: &self.blocks.sweeping,
+        }
+    }
+
+    #[cfg(feature = "multi_threaded")]
+    pub unsafe fn sweep_locked(&self, live_mark: GcMark, _guard: &BlockGuard<'_>) {
         self.heap.sweep(live_mark.into())
     }
 
+    #[cfg(not(feature = "multi_threaded"))]
     pub fn get_size(&self) -> u64 {
         self.heap.size() as u64
     }
+
+    // While the heap is being swept its size cannot be measured, so the size
+    // from before the sweep is returned instead.
+    #[cfg(feature = "multi_threaded")]
+    pub fn get_size(&self) -> u64 {
+        if let Ok(_guard) = self.blocks.lock.try_read() {
+            self.size.store(self.heap.size() as u64, Ordering::Relaxed);
+        }
+
+        self.size.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(feature = "multi_threaded")]
+pub struct BlockGuard<'a> {
+    guard: Option<RwLockWriteGuard<'a, ()>>,
+    sweeping: &'a AtomicBool,
+}
+
+#[cfg(feature = "multi_threaded")]
+impl Drop for BlockGuard<'_> {
+    fn drop(&mut self) {
+        // the lock must be released first, so that allocators which saw the
+        // sweep in flight are never left waiting
+        drop(self.guard.take());
+        self.sweeping.store(false, Ordering::Release);
+    }
 }
 
 pub unsafe fn mark(ptr: *mut u8, layout: Layout, mark: GcMark) {
@@ -0,0 +1,295 @@
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+/// A graph of the live values in an arena, obtained by calling
+/// [`crate::Arena::heap_dump`].
+///
+/// The graph can be written out as JSON with [`HeapDump::write_json`], or as
+/// a Graphviz DOT graph with [`HeapDump::write_dot`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HeapDump {
+    /// Every value reached by the trace, ordered by address.
+    pub nodes: Vec<HeapNode>,
+    /// Every pointer between values which was traced.
+    pub edges: Vec<HeapEdge>,
+}
+
+/// A live value in a [`HeapDump`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HeapNode {
+    /// The address of the value, which identifies it within the dump.
+    pub address: usize,
+    /// The name of the value's type, as given by [`core::any::type_name`].
+    pub type_name: &'static str,
+    /// The size of the value's allocation in bytes, including its GC header.
+    pub size: usize,
+    /// The mark the value held when it was reached by the trace, before
+    /// being marked by it.
+    pub mark: u8,
+}
+
+/// The chain of values through which a value is reachable from the root,
+/// obtained by calling [`crate::Arena::retention_path`] or
+/// [`HeapDump::retention_path`].
+///
+/// The first node is held directly by the arena's root, a [`crate::Handle`],
+/// or a mutator's stack, and each following node is pointed to by the one
+/// before it. The last node is the value the path was requested for.
+///
+/// A path is displayed as each hop's type and address, starting from the
+/// root:
+///
+/// ```text
+/// root -> alloc::vec::Vec<usize> (0x7f3a1c000010) -> usize (0x7f3a1c000040)
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetentionPath {
+    /// The values along the path, starting from the one held by the root.
+    pub nodes: Vec<HeapNode>,
+}
+
+/// A pointer from one value in a [`HeapDump`] to another.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HeapEdge {
+    /// The address of the value holding the pointer, or `None` if the
+    /// pointer is held by the arena's root, a [`crate::Handle`], or a
+    /// mutator's stack.
+    pub from: Option<usize>,
+    /// The address of the value pointed to.
+    pub to: usize,
+}
+
+impl HeapDump {
+    /// Writes the dump as a JSON object with a `nodes` and an `edges` array.
+    /// Addresses are written as hex strings, and pointers held by the root
+    /// have a `from` of `null`.
+    ///
+    /// ```json
+    /// {
+    ///   "nodes": [{"address": "0x1000", "type": "usize", "size": 16, "mark": 1}],
+    ///   "edges": [{"from": null, "to": "0x1000"}]
+    /// }
+    /// ```
+    pub fn write_json<W: Write>(&self, out: &mut W) -> fmt::Result {
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"nodes\": [")?;
+
+        for (i, node) in self.nodes.iter().enumerate() {
+            write!(out, "    {{\"address\": \"{:#x}\", \"type\": ", node.address)?;
+            write_json_string(out, node.type_name)?;
+            write!(out, ", \"size\": {}, \"mark\": {}}}", node.size, node.mark)?;
+            writeln!(out, "{}", if i + 1 < self.nodes.len() { "," } else { "" })?;
+        }
+
+        writeln!(out, "  ],")?;
+        writeln!(out, "  \"edges\": [")?;
+
+        for (i, edge) in self.edges.iter().enumerate() {
+            match edge.from {
+                Some(from) => write!(out, "    {{\"from\": \"{:#x}\", ", from)?,
+                None => write!(out, "    {{\"from\": null, ")?,
+            }
+
+            write!(out, "\"to\": \"{:#x}\"}}", edge.to)?;
+            writeln!(out, "{}", if i + 1 < self.edges.len() { "," } else { "" })?;
+        }
+
+        writeln!(out, "  ]")?;
+        writeln!(out, "}}")
+    }
+
+    /// Writes the dump as a Graphviz DOT digraph, in which each value is
+    /// labeled with its type, address and size, and the root is drawn as a
+    /// box.
+    pub fn write_dot<W: Write>(&self, out: &mut W) -> fmt::Result {
+        writeln!(out, "digraph heap {{")?;
+        writeln!(out, "  root [shape=box];")?;
+
+        for node in self.nodes.iter() {
+            write!(out, "  n{:x} [label=\"", node.address)?;
+            write_dot_escaped(out, node.type_name)?;
+            writeln!(out, "\\n{:#x}\\n{} bytes\"];", node.address, node.size)?;
+        }
+
+        for edge in self.edges.iter() {
+            match edge.from {
+                Some(from) => write!(out, "  n{:x}", from)?,
+                None => write!(out, "  root")?,
+            }
+
+            writeln!(out, " -> n{:x};", edge.to)?;
+        }
+
+        writeln!(out, "}}")
+    }
+
+    /// Returns the node at the given address.
+    pub fn get(&self, address: usize) -> Option<&HeapNode> {
+        self.nodes
+            .binary_search_by_key(&address, |node| node.address)
+            .ok()
+            .map(|i| &self.nodes[i])
+    }
+
+    /// Returns a shortest chain of values from the root to the value at the
+    /// given address, or `None` if no such value was recorded.
+    pub fn retention_path(&self, address: usize) -> Option<RetentionPath> {
+        self.get(address)?;
+
+        let mut children: BTreeMap<Option<usize>, Vec<usize>> = BTreeMap::new();
+
+        for edge in self.edges.iter() {
+            children.entry(edge.from).or_default().push(edge.to);
+        }
+
+        // a breadth first search from the root, recording which value each
+        // value was first reached from
+        let mut reached_from: BTreeMap<usize, Option<usize>> = BTreeMap::new();
+        let mut queue = VecDeque::from(vec![None]);
+
+        while let Some(from) = queue.pop_front() {
+            for &to in children.get(&from).into_iter().flatten() {
+                if reached_from.contains_key(&to) {
+                    continue;
+                }
+
+                reached_from.insert(to, from);
+
+                if to == address {
+                    queue.clear();
+                    break;
+                }
+
+                queue.push_back(Some(to));
+            }
+        }
+
+        let mut nodes = vec![];
+        let mut current = Some(address);
+
+        while let Some(address) = current {
+            nodes.push(*self.get(address)?);
+            current = *reached_from.get(&address)?;
+        }
+
+        nodes.reverse();
+
+        Some(RetentionPath { nodes })
+    }
+
+    /// Returns the dump as JSON, see [`HeapDump::write_json`].
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        self.write_json(&mut json).expect("writing to a String cannot fail");
+        json
+    }
+
+    /// Returns the dump as a DOT graph, see [`HeapDump::write_dot`].
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        self.write_dot(&mut dot).expect("writing to a String cannot fail");
+        dot
+    }
+}
+
+impl fmt::Display for RetentionPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "root")?;
+
+        for node in self.nodes.iter() {
+            write!(f, " -> {} ({:#x})", node.type_name, node.address)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_json_string<W: Write>(out: &mut W, s: &str) -> fmt::Result {
+    out.write_char('"')?;
+
+    for c in s.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+
+    out.write_char('"')
+}
+
+fn write_dot_escaped<W: Write>(out: &mut W, s: &str) -> fmt::Result {
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                out.write_char('\\')?;
+                out.write_char(c)?;
+            }
+            c => out.write_char(c)?,
+        }
+    }
+
+    Ok(())
+}
+
+// Records the values marked by a tracer, and the pointers it traced to reach
+// them. Each tracer keeps its own, which are merged once it has finished.
+#[derive(Default)]
+pub struct DumpRecorder {
+    nodes: Vec<HeapNode>,
+    edges: Vec<(Option<usize>, usize)>,
+    // the value whose pointers are currently being traced
+    parent: Option<usize>,
+}
+
+impl DumpRecorder {
+    pub fn set_parent(&mut self, parent: Option<usize>) {
+        self.parent = parent;
+    }
+
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+
+    pub fn record_node(&mut self, node: HeapNode) {
+        self.nodes.push(node);
+    }
+
+    pub fn record_edge(&mut self, to: usize) {
+        self.edges.push((self.parent, to));
+    }
+
+    pub fn merge(&mut self, mut other: DumpRecorder) {
+        self.nodes.append(&mut other.nodes);
+        self.edges.append(&mut other.edges);
+    }
+
+    // Pointers traced from anything other than a recorded value, such as the
+    // arena's root, are attributed to the root.
+    pub fn finish(mut self) -> HeapDump {
+        self.nodes.sort_by_key(|node| node.address);
+        self.nodes.dedup_by_key(|node| node.address);
+
+        let addresses: BTreeSet<usize> = self.nodes.iter().map(|node| node.address).collect();
+        let mut edges: Vec<HeapEdge> = self
+            .edges
+            .into_iter()
+            .map(|(from, to)| HeapEdge {
+                from: from.filter(|from| addresses.contains(from)),
+                to,
+            })
+            .collect();
+
+        edges.sort();
+        edges.dedup();
+
+        HeapDump {
+            nodes: self.nodes,
+            edges,
+        }
+    }
+}
@@ -49,9 +49,10 @@
 //! Essentially when a value is traced the tracer will mark the value as live,
 //! and call trace on all its inner pointers to GC values.
 //!
-//! There are 2 types of GC pointers:
+//! There are 3 types of GC pointers:
 //! * [`gc::Gc`]
 //! * [`gc::GcOpt`]
+//! * [`gc::GcWeak`], which does not keep the value it points at alive
 //!
 //! A type may also derive [`TraceLeaf`], if it contains no GC pointers.
 //! [`TraceLeaf`] allows for easier interior mutability.
@@ -131,30 +132,48 @@ extern crate self as sandpit;
 
 mod arena;
 mod barrier;
+mod census;
 mod config;
 mod debug;
+mod ephemeron;
+mod event;
+mod finalize;
 mod gc;
 mod gc_sync;
+mod handle;
 mod header;
 mod heap;
+mod heap_dump;
 mod metrics;
 mod mutator;
 mod pointee;
+mod policy;
+mod stress;
 mod tagged;
 mod trace;
 mod vec;
+mod verify;
 
 /// Re-exported from ForLt. Used in making the root of an arena.
 pub use higher_kinded_types::ForLt as Root;
 
 pub use arena::Arena;
 pub use barrier::{InnerBarrier, WriteBarrier};
-pub use config::Config;
-pub use gc::{Gc, GcOpt};
+pub use census::{HeapCensus, TypeCensus};
+pub use config::{Config, ConfigBuilder, ConfigError, HeapLimitPolicy};
+pub use ephemeron::Ephemeron;
+pub use event::{CollectionKind, GcEvent, GcEventListener};
+pub use finalize::Finalize;
+pub use gc::{Gc, GcOpt, GcWeak};
 pub use gc_sync::GcSync;
-pub use metrics::Metrics;
+pub use handle::Handle;
+pub use heap::AllocError;
+pub use heap_dump::{HeapDump, HeapEdge, HeapNode, RetentionPath};
+pub use metrics::{Metrics, MetricsSnapshot, Percentiles, TriggerMetrics};
 pub use mutator::Mutator;
+pub use policy::{CollectionDecision, CollectionPolicy, DefaultPolicy};
 pub use sandpit_derive::{GcSync, Tag, Trace, TraceLeaf};
+pub use stress::{StressMode, StressTrigger};
 pub use tagged::{Tag, Tagged};
 pub use trace::{Trace, TraceLeaf};
 pub use vec::GcVec;
@@ -1,8 +1,15 @@
-use core::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use core::fmt;
+use core::sync::atomic::{fence, AtomicU64, AtomicU8, Ordering};
+use core::time::Duration;
 
-/// A 'snapshot' of the metrics relevant to the GC's internal triggers.
+/// The metrics relevant to the GC's internal triggers.
 ///
-/// Obtained by calling [`crate::Arena::metrics`].
+/// Obtained by calling [`crate::Arena::metrics`]. Each field is updated
+/// independently, so reading several of them may observe a collection
+/// partway through updating them. Use [`crate::Arena::metrics_snapshot`] to
+/// read them consistently.
+///
+/// Durations are recorded in nanoseconds.
 #[derive(Debug)]
 pub struct Metrics {
     /// Number of major collections that have occured.
@@ -11,10 +18,12 @@ pub struct Metrics {
     /// Number of minor collections that have occured.
     pub minor_collections: AtomicU64,
 
-    /// Average time that takes for a major collection to complete.
+    /// Average time in nanoseconds that takes for a major collection to
+    /// complete.
     pub major_collect_avg_time: AtomicU64,
 
-    /// Average time that takes for a minor collection to complete.
+    /// Average time in nanoseconds that takes for a minor collection to
+    /// complete.
     pub minor_collect_avg_time: AtomicU64,
 
     /// Once the old objects count reaches this number, a major collection will
@@ -31,12 +40,33 @@ pub struct Metrics {
     /// The arena size at the start of the last collection.
     pub prev_arena_size: AtomicU64,
 
+    /// Total size of the allocations made since the last collection began
+    /// tracing.
+    pub allocated_bytes: AtomicU64,
+
     /// The current state of the GC.
     pub state: AtomicU8,
 
+    /// Number of times a mutator has been blocked by the timeslicer.
+    pub timeslice_yields: AtomicU64,
+
+    /// Longest time in nanoseconds a mutator has been blocked by the
+    /// timeslicer.
     pub max_yield_time: AtomicU64,
+
+    /// Average time in nanoseconds a mutator is blocked by the timeslicer.
     pub avg_yield_time: AtomicU64,
+
     pub monitor_is_on: bool,
+
+    major_collection_times: Histogram,
+    minor_collection_times: Histogram,
+    major_pause_times: Histogram,
+    minor_pause_times: Histogram,
+
+    // Odd while a collection is recording its results, allowing snapshots to
+    // retry rather than observe a partial update.
+    update_seq: AtomicU64,
 }
 
 impl Metrics {
@@ -46,14 +76,126 @@ impl Metrics {
             minor_collections: AtomicU64::new(0),
             major_collect_avg_time: AtomicU64::new(0),
             minor_collect_avg_time: AtomicU64::new(0),
+            timeslice_yields: AtomicU64::new(0),
             max_yield_time: AtomicU64::new(0),
             avg_yield_time: AtomicU64::new(0),
             max_old_objects: AtomicU64::new(0),
             old_objects_count: AtomicU64::new(0),
             arena_size: AtomicU64::new(0),
             prev_arena_size: AtomicU64::new(0),
+            allocated_bytes: AtomicU64::new(0),
             state: AtomicU8::new(GC_STATE_SLEEPING),
             monitor_is_on: true,
+            major_collection_times: Histogram::new(),
+            minor_collection_times: Histogram::new(),
+            major_pause_times: Histogram::new(),
+            minor_pause_times: Histogram::new(),
+            update_seq: AtomicU64::new(0),
+        }
+    }
+
+    // Records the duration of a collection, and of the pause during which no
+    // mutators could run. The collection count is incremented after the
+    // average is updated, so that the average is taken over the previous
+    // collections.
+    pub(crate) fn record_major_collection(&self, duration: Duration, pause: Duration) {
+        self.update_major_collection_avg_time(duration.as_nanos() as u64);
+        self.major_collection_times.record(duration);
+        self.major_pause_times.record(pause);
+        self.major_collections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_minor_collection(&self, duration: Duration, pause: Duration) {
+        self.update_minor_collection_avg_time(duration.as_nanos() as u64);
+        self.minor_collection_times.record(duration);
+        self.minor_pause_times.record(pause);
+        self.minor_collections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // Marks the start of a set of updates which snapshots must observe
+    // together. Both the collector and a concurrent sweep may update, so an
+    // update waits for any other to end first.
+    pub(crate) fn begin_update(&self) {
+        loop {
+            let seq = self.update_seq.load(Ordering::Relaxed);
+
+            if seq % 2 == 0
+                && self
+                    .update_seq
+                    .compare_exchange_weak(seq, seq + 1, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+            {
+                break;
+            }
+
+            core::hint::spin_loop();
+        }
+
+        fence(Ordering::Release);
+    }
+
+    pub(crate) fn end_update(&self) {
+        self.update_seq.fetch_add(1, Ordering::Release);
+    }
+
+    /// Reads every metric, retrying if a collection was recording its results
+    /// at the same time, so that the values a collection updates together
+    /// are always consistent with one another.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.read_consistent(Self::read_snapshot)
+    }
+
+    /// Reads the metrics a [`crate::CollectionPolicy`] decides from, as
+    /// consistently as [`Metrics::snapshot`], but without the cost of
+    /// reading the collection time percentiles.
+    pub fn trigger_metrics(&self) -> TriggerMetrics {
+        self.read_consistent(|metrics| TriggerMetrics {
+            max_old_objects: metrics.get_max_old_objects(),
+            old_objects_count: metrics.get_old_objects_count(),
+            arena_size: metrics.get_arena_size(),
+            prev_arena_size: metrics.get_prev_arena_size(),
+            allocated_bytes: metrics.get_allocated_bytes(),
+        })
+    }
+
+    fn read_consistent<T>(&self, read: impl Fn(&Self) -> T) -> T {
+        loop {
+            let seq = self.update_seq.load(Ordering::Acquire);
+
+            if seq % 2 == 1 {
+                core::hint::spin_loop();
+                continue;
+            }
+
+            let value = read(self);
+
+            fence(Ordering::Acquire);
+
+            if self.update_seq.load(Ordering::Relaxed) == seq {
+                return value;
+            }
+        }
+    }
+
+    fn read_snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            major_collections: self.get_major_collections(),
+            minor_collections: self.get_minor_collections(),
+            major_collect_avg_time: Duration::from_nanos(self.get_major_collect_avg_time()),
+            minor_collect_avg_time: Duration::from_nanos(self.get_minor_collect_avg_time()),
+            major_collection_times: self.major_collection_times.percentiles(),
+            minor_collection_times: self.minor_collection_times.percentiles(),
+            major_pause_times: self.major_pause_times.percentiles(),
+            minor_pause_times: self.minor_pause_times.percentiles(),
+            max_old_objects: self.get_max_old_objects(),
+            old_objects_count: self.get_old_objects_count(),
+            arena_size: self.get_arena_size(),
+            prev_arena_size: self.get_prev_arena_size(),
+            allocated_bytes: self.get_allocated_bytes(),
+            state: self.get_state(),
+            timeslice_yields: self.get_timeslice_yields(),
+            max_yield_time: Duration::from_nanos(self.get_max_yield_time()),
+            avg_yield_time: Duration::from_nanos(self.get_avg_yield_time()),
         }
     }
 
@@ -105,10 +247,24 @@ impl Metrics {
         self.prev_arena_size.load(Ordering::Relaxed)
     }
 
+    pub fn get_allocated_bytes(&self) -> u64 {
+        self.allocated_bytes.load(Ordering::Relaxed)
+    }
+
+    // The size of the arena after the last collection plus everything
+    // allocated since, used to enforce `Config::max_heap_bytes`.
+    pub(crate) fn estimated_heap_size(&self) -> u64 {
+        self.get_prev_arena_size() + self.get_allocated_bytes()
+    }
+
     pub fn get_state(&self) -> u8 {
         self.state.load(Ordering::Relaxed)
     }
 
+    pub fn get_timeslice_yields(&self) -> u64 {
+        self.timeslice_yields.load(Ordering::Relaxed)
+    }
+
     pub fn get_max_yield_time(&self) -> u64 {
         self.max_yield_time.load(Ordering::Relaxed)
     }
@@ -118,6 +274,161 @@ impl Metrics {
     }
 }
 
+/// A copy of an arena's [`Metrics`], taken at a single point in time.
+///
+/// Obtained by calling [`crate::Arena::metrics_snapshot`]. Pause times are the
+/// portion of each collection during which no mutator could run, which is
+/// the entire collection in single-threaded mode.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub major_collections: u64,
+    pub minor_collections: u64,
+    pub major_collect_avg_time: Duration,
+    pub minor_collect_avg_time: Duration,
+    pub major_collection_times: Percentiles,
+    pub minor_collection_times: Percentiles,
+    pub major_pause_times: Percentiles,
+    pub minor_pause_times: Percentiles,
+    pub max_old_objects: u64,
+    pub old_objects_count: u64,
+    pub arena_size: u64,
+    pub prev_arena_size: u64,
+    pub allocated_bytes: u64,
+    pub state: u8,
+    pub timeslice_yields: u64,
+    pub max_yield_time: Duration,
+    pub avg_yield_time: Duration,
+}
+
+/// The metrics a [`crate::CollectionPolicy`] decides from, taken at a single
+/// point in time.
+///
+/// This is the subset of a [`MetricsSnapshot`] which is cheap enough to read
+/// each time a mutator checks whether it should yield.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TriggerMetrics {
+    pub max_old_objects: u64,
+    pub old_objects_count: u64,
+    pub arena_size: u64,
+    pub prev_arena_size: u64,
+    pub allocated_bytes: u64,
+}
+
+/// The distribution of a set of recorded durations.
+///
+/// Percentiles are approximate, being accurate to within 12.5% of the true
+/// value, while the max is exact.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Percentiles {
+    /// The number of durations recorded.
+    pub count: u64,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+// Each power of two range of nanoseconds is split into this many buckets.
+const HISTOGRAM_SUB_BUCKETS: usize = 8;
+const HISTOGRAM_SUB_BUCKET_BITS: u32 = HISTOGRAM_SUB_BUCKETS.trailing_zeros();
+const HISTOGRAM_BUCKETS: usize = (64 - HISTOGRAM_SUB_BUCKET_BITS as usize + 1) * HISTOGRAM_SUB_BUCKETS;
+
+// A log-linear histogram of durations in nanoseconds, which may be recorded
+// to concurrently without locking.
+struct Histogram {
+    buckets: [AtomicU64; HISTOGRAM_BUCKETS],
+    count: AtomicU64,
+    max: AtomicU64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: core::array::from_fn(|_| AtomicU64::new(0)),
+            count: AtomicU64::new(0),
+            max: AtomicU64::new(0),
+        }
+    }
+
+    fn record(&self, duration: Duration) {
+        let nanos = duration.as_nanos().min(u64::MAX as u128) as u64;
+
+        self.buckets[Self::bucket_index(nanos)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.max.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    fn percentiles(&self) -> Percentiles {
+        let max = self.max.load(Ordering::Relaxed);
+
+        Percentiles {
+            count: self.count.load(Ordering::Relaxed),
+            p50: Duration::from_nanos(self.percentile(0.50).min(max)),
+            p95: Duration::from_nanos(self.percentile(0.95).min(max)),
+            p99: Duration::from_nanos(self.percentile(0.99).min(max)),
+            max: Duration::from_nanos(max),
+        }
+    }
+
+    // The upper bound of the bucket containing the given percentile.
+    fn percentile(&self, percentile: f64) -> u64 {
+        let count = self.count.load(Ordering::Relaxed);
+
+        if count == 0 {
+            return 0;
+        }
+
+        let target = ((percentile * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+
+        for (index, bucket) in self.buckets.iter().enumerate() {
+            seen += bucket.load(Ordering::Relaxed);
+
+            if seen >= target {
+                return Self::bucket_upper_bound(index);
+            }
+        }
+
+        u64::MAX
+    }
+
+    // Values below the number of sub buckets each have their own bucket,
+    // larger values are bucketed by their highest set bit, and the bits
+    // which follow it.
+    fn bucket_index(nanos: u64) -> usize {
+        if nanos < HISTOGRAM_SUB_BUCKETS as u64 {
+            return nanos as usize;
+        }
+
+        let exponent = 63 - nanos.leading_zeros();
+        let shift = exponent - HISTOGRAM_SUB_BUCKET_BITS;
+        let sub_bucket = (nanos >> shift) as usize & (HISTOGRAM_SUB_BUCKETS - 1);
+
+        (shift as usize + 1) * HISTOGRAM_SUB_BUCKETS + sub_bucket
+    }
+
+    fn bucket_upper_bound(index: usize) -> u64 {
+        if index < HISTOGRAM_SUB_BUCKETS {
+            return index as u64;
+        }
+
+        let shift = (index / HISTOGRAM_SUB_BUCKETS - 1) as u32;
+        let sub_bucket = (index % HISTOGRAM_SUB_BUCKETS) as u64;
+        let lower = (HISTOGRAM_SUB_BUCKETS as u64 + sub_bucket) << shift;
+
+        lower + ((1 << shift) - 1)
+    }
+}
+
+impl fmt::Debug for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Histogram")
+            .field("count", &self.count.load(Ordering::Relaxed))
+            .field("max", &self.max.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
 pub fn update_avg_u64(running_avg: &AtomicU64, new_value: u64, sample_size: u64) {
     let avg = running_avg.load(Ordering::Relaxed);
     let update = new_value.abs_diff(avg) / (sample_size + 1);
@@ -138,3 +449,40 @@ pub const GC_STATE_TRACING: u8 = 1;
 pub const GC_STATE_SWEEPING: u8 = 2;
 #[cfg(feature = "multi_threaded")]
 pub const GC_STATE_WAITING_ON_MUTATORS: u8 = 3;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_cover_every_value() {
+        for nanos in [0, 1, 7, 8, 9, 15, 16, 17, 1000, 123_456_789, u64::MAX] {
+            let index = Histogram::bucket_index(nanos);
+
+            assert!(index < HISTOGRAM_BUCKETS);
+            assert!(Histogram::bucket_upper_bound(index) >= nanos);
+            assert!(Histogram::bucket_upper_bound(index) - nanos <= nanos / 8);
+        }
+    }
+
+    #[test]
+    fn histogram_percentiles() {
+        let histogram = Histogram::new();
+
+        for millis in 1..=100 {
+            histogram.record(Duration::from_millis(millis));
+        }
+
+        let percentiles = histogram.percentiles();
+        let within = |actual: Duration, expected: u64| {
+            let expected = Duration::from_millis(expected);
+            actual >= expected && actual <= expected + expected / 8
+        };
+
+        assert_eq!(percentiles.count, 100);
+        assert!(within(percentiles.p50, 50));
+        assert!(within(percentiles.p95, 95));
+        assert!(within(percentiles.p99, 99));
+        assert_eq!(percentiles.max, Duration::from_millis(100));
+    }
+}
@@ -1,12 +1,20 @@
-use crate::heap::Allocator;
+use crate::config::{Config, HeapLimitPolicy};
+use crate::finalize::{Finalize, FinalizeJob};
+use crate::handle::{Handle, RootSet};
+use crate::heap::{AllocError, Allocator};
+use crate::stress::StressPoint;
 
 use super::gc::Gc;
 use super::header::{GcHeader, GcMark, SizedHeader, SliceHeader, StrHeader};
 use super::pointee::Thin;
-use super::pointee::{sized_alloc_layout, slice_alloc_layout, str_alloc_layout};
+use super::pointee::{sized_alloc_layout, try_slice_alloc_layout, try_str_alloc_layout};
 use super::trace::{Collector, Trace, TraceJob};
-use core::cell::RefCell;
+use alloc::sync::Arc;
+use alloc::alloc::Layout;
+use core::cell::{Cell, RefCell};
 use core::ptr::{copy, write, NonNull};
+use core::sync::atomic::Ordering;
+use higher_kinded_types::ForLt;
 use std::collections::HashSet;
 
 /// Allows for allocation and mutation within the GC arena.
@@ -56,11 +64,18 @@ use std::collections::HashSet;
 /// The mutator therefore holds a lock, that the tracer threads use
 /// to be able to identify if the mutation contexts have all exited at
 /// which point the Gc will free memory and then allow for mutation to resume.
+///
+/// Alternatively, a mutator may call [`Mutator::safepoint`], explicitly
+/// declaring the values it still holds, to be parked while memory is freed
+/// without exiting the mutation callback.
 pub struct Mutator<'gc> {
     collector: &'gc Collector,
+    // read when the mutator is created, rather than on every allocation, and
+    // again after it is parked, as the collection may have updated it
+    config: RefCell<Config>,
     allocator: Allocator,
     rescan: RefCell<HashSet<TraceJob>>,
-    mark: GcMark,
+    mark: Cell<GcMark>,
 }
 
 impl<'gc> Drop for Mutator<'gc> {
@@ -80,8 +95,9 @@ impl<'gc> Mutator<'gc> {
         Self {
             allocator,
             collector,
+            config: RefCell::new(collector.config()),
             rescan: RefCell::new(HashSet::new()),
-            mark,
+            mark: Cell::new(mark),
         }
     }
 
@@ -101,10 +117,27 @@ impl<'gc> Mutator<'gc> {
     /// });
     /// ```
     pub fn alloc<T: Trace>(&self, value: T) -> Gc<'gc, T> {
+        self.try_alloc(value).expect("Failed to allocate")
+    }
+
+    /// Same as [`Mutator::alloc`], but returns an [`AllocError`] instead of
+    /// panicking if the allocation fails.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use sandpit::{Arena, Gc, Root};
+    /// # let arena: Arena<Root![Gc<'_, usize>]> = Arena::new(|mu| {
+    /// #    Gc::new(mu, 123)
+    /// # });
+    /// arena.mutate(|mu, root| {
+    ///     let a = mu.try_alloc(456).unwrap();
+    /// });
+    /// ```
+    pub fn try_alloc<T: Trace>(&self, value: T) -> Result<Gc<'gc, T>, AllocError> {
         let (alloc_layout, val_offset) = sized_alloc_layout::<T>();
 
         unsafe {
-            let ptr = self.allocator.alloc(alloc_layout) as *mut u8;
+            let ptr = self.alloc_layout(alloc_layout)? as *mut u8;
             // SAFETY: the alloc layout was extended to have capacity
             // for the header and object to be written into.
 
@@ -115,12 +148,43 @@ impl<'gc> Mutator<'gc> {
             let header_ptr = ptr.cast();
 
             write(val_ptr, value);
-            write(header_ptr, SizedHeader::<T>::new(self.mark));
+            write(header_ptr, SizedHeader::<T>::new(self.mark.get()));
 
-            Gc::from_ptr(val_ptr)
+            Ok(Gc::from_ptr(val_ptr))
         }
     }
 
+    /// Allocates a value which will be finalized once the GC determines it is
+    /// unreachable. See [`crate::Finalize`] for more details.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use sandpit::{Arena, Finalize, Gc, Root, Trace};
+    /// # let arena: Arena<Root![Gc<'_, usize>]> = Arena::new(|mu| {
+    /// #    Gc::new(mu, 123)
+    /// # });
+    /// #[derive(Trace)]
+    /// struct Socket(usize);
+    ///
+    /// unsafe impl Finalize for Socket {
+    ///     fn finalize(&mut self) {
+    ///         // close the socket
+    ///     }
+    /// }
+    ///
+    /// arena.mutate(|mu, root| {
+    ///     let socket = mu.alloc_finalized(Socket(8080));
+    /// });
+    /// ```
+    pub fn alloc_finalized<T: Finalize>(&self, value: T) -> Gc<'gc, T> {
+        let gc = self.alloc(value);
+
+        self.collector
+            .register_finalizer(FinalizeJob::new::<T>(gc.as_thin()));
+
+        gc
+    }
+
     /// Alloc a `Gc<[T]>` with specified length and with each index set to value.
     ///
     /// # Example
@@ -140,10 +204,33 @@ impl<'gc> Mutator<'gc> {
     /// });
     /// ```
     pub fn alloc_array<T: Trace + Copy>(&'gc self, value: T, len: usize) -> Gc<'gc, [T]> {
-        let (alloc_layout, slice_offset) = slice_alloc_layout::<T>(len);
+        self.try_alloc_array(value, len).expect("Failed to allocate")
+    }
+
+    /// Same as [`Mutator::alloc_array`], but returns an [`AllocError`] instead
+    /// of panicking if the allocation fails.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use sandpit::{AllocError, Arena, Gc, Root};
+    /// # let arena: Arena<Root![Gc<'_, usize>]> = Arena::new(|mu| {
+    /// #    Gc::new(mu, 123)
+    /// # });
+    /// arena.mutate(|mu, root| {
+    ///     let result = mu.try_alloc_array(0u64, usize::MAX);
+    ///
+    ///     assert_eq!(result.err(), Some(AllocError::TooLarge));
+    /// });
+    /// ```
+    pub fn try_alloc_array<T: Trace + Copy>(
+        &'gc self,
+        value: T,
+        len: usize,
+    ) -> Result<Gc<'gc, [T]>, AllocError> {
+        let (alloc_layout, slice_offset) = try_slice_alloc_layout::<T>(len)?;
 
         unsafe {
-            let ptr = self.allocator.alloc(alloc_layout) as *mut u8;
+            let ptr = self.alloc_layout(alloc_layout)? as *mut u8;
             let header_ptr = ptr.cast();
             let slice_ptr: *mut T = ptr.add(slice_offset).cast();
 
@@ -152,9 +239,9 @@ impl<'gc> Mutator<'gc> {
             }
 
             let slice: *const [T] = core::ptr::slice_from_raw_parts(slice_ptr, len);
-            write(header_ptr, SliceHeader::<T>::new(self.mark, slice.len()));
+            write(header_ptr, SliceHeader::<T>::new(self.mark.get(), slice.len()));
 
-            Gc::from_ptr(slice)
+            Ok(Gc::from_ptr(slice))
         }
     }
 
@@ -175,19 +262,28 @@ impl<'gc> Mutator<'gc> {
     /// });
     /// ```
     pub fn alloc_array_from_slice<T: Trace + Copy>(&'gc self, slice: &[T]) -> Gc<'gc, [T]> {
-        let (alloc_layout, slice_offset) = slice_alloc_layout::<T>(slice.len());
+        self.try_alloc_array_from_slice(slice).expect("Failed to allocate")
+    }
+
+    /// Same as [`Mutator::alloc_array_from_slice`], but returns an
+    /// [`AllocError`] instead of panicking if the allocation fails.
+    pub fn try_alloc_array_from_slice<T: Trace + Copy>(
+        &'gc self,
+        slice: &[T],
+    ) -> Result<Gc<'gc, [T]>, AllocError> {
+        let (alloc_layout, slice_offset) = try_slice_alloc_layout::<T>(slice.len())?;
 
         unsafe {
-            let ptr = self.allocator.alloc(alloc_layout) as *mut u8;
+            let ptr = self.alloc_layout(alloc_layout)? as *mut u8;
             let header_ptr = ptr.cast();
             let slice_ptr: *mut T = ptr.add(slice_offset).cast();
 
             copy(slice.as_ptr(), slice_ptr, slice.len());
 
             let slice: *const [T] = core::ptr::slice_from_raw_parts(slice_ptr, slice.len());
-            write(header_ptr, SliceHeader::<T>::new(self.mark, slice.len()));
+            write(header_ptr, SliceHeader::<T>::new(self.mark.get(), slice.len()));
 
-            Gc::from_ptr(slice)
+            Ok(Gc::from_ptr(slice))
         }
     }
 
@@ -207,15 +303,31 @@ impl<'gc> Mutator<'gc> {
     ///     }
     /// });
     /// ```
-    pub fn alloc_array_from_fn<T, F>(&'gc self, len: usize, mut cb: F) -> Gc<'gc, [T]>
+    pub fn alloc_array_from_fn<T, F>(&'gc self, len: usize, cb: F) -> Gc<'gc, [T]>
     where
         T: Trace,
         F: FnMut(usize) -> T,
     {
-        let (alloc_layout, slice_offset) = slice_alloc_layout::<T>(len);
+        self.try_alloc_array_from_fn(len, cb).expect("Failed to allocate")
+    }
+
+    /// Same as [`Mutator::alloc_array_from_fn`], but returns an
+    /// [`AllocError`] instead of panicking if the allocation fails.
+    ///
+    /// The closure is not called if the allocation fails.
+    pub fn try_alloc_array_from_fn<T, F>(
+        &'gc self,
+        len: usize,
+        mut cb: F,
+    ) -> Result<Gc<'gc, [T]>, AllocError>
+    where
+        T: Trace,
+        F: FnMut(usize) -> T,
+    {
+        let (alloc_layout, slice_offset) = try_slice_alloc_layout::<T>(len)?;
 
         unsafe {
-            let ptr = self.allocator.alloc(alloc_layout) as *mut u8;
+            let ptr = self.alloc_layout(alloc_layout)? as *mut u8;
             let header_ptr = ptr.cast();
             let slice_ptr: *mut T = ptr.add(slice_offset).cast();
 
@@ -225,9 +337,9 @@ impl<'gc> Mutator<'gc> {
             }
 
             let slice: *const [T] = core::ptr::slice_from_raw_parts(slice_ptr, len);
-            write(header_ptr, SliceHeader::<T>::new(self.mark, len));
+            write(header_ptr, SliceHeader::<T>::new(self.mark.get(), len));
 
-            Gc::from_ptr(slice)
+            Ok(Gc::from_ptr(slice))
         }
     }
 
@@ -246,22 +358,62 @@ impl<'gc> Mutator<'gc> {
     /// });
     /// ```
     pub fn alloc_str(&'gc self, s: &str) -> Gc<'gc, str> {
-        let (alloc_layout, str_offset) = str_alloc_layout(s.len());
+        self.try_alloc_str(s).expect("Failed to allocate")
+    }
+
+    /// Same as [`Mutator::alloc_str`], but returns an [`AllocError`] instead
+    /// of panicking if the allocation fails.
+    pub fn try_alloc_str(&'gc self, s: &str) -> Result<Gc<'gc, str>, AllocError> {
+        let (alloc_layout, str_offset) = try_str_alloc_layout(s.len())?;
 
         unsafe {
-            let ptr = self.allocator.alloc(alloc_layout) as *mut u8;
+            let ptr = self.alloc_layout(alloc_layout)? as *mut u8;
             let header_ptr = ptr.cast();
             let str_ptr: *mut u8 = ptr.add(str_offset);
 
             copy(s.as_ptr(), str_ptr, s.len());
 
             let str_slice: *const str = core::ptr::slice_from_raw_parts(str_ptr, s.len()) as *const str;
-            write(header_ptr, StrHeader::new(self.mark, s.len()));
+            write(header_ptr, StrHeader::new(self.mark.get(), s.len()));
 
-            Gc::from_ptr(str_slice)
+            Ok(Gc::from_ptr(str_slice))
         }
     }
 
+    /// Register `gc` as an additional root of this mutator's arena, returning
+    /// a [`Handle`] which may be held outside of a mutation context.
+    ///
+    /// The value, and everything reachable from it, will not be freed for as
+    /// long as the handle exists. See [`Handle`] for more details.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use sandpit::{Arena, Gc, Handle, Root};
+    /// # let arena: Arena<Root![()]> = Arena::new(|_| ());
+    /// let handle: Handle<Root![Gc<'_, usize>]> = arena.mutate(|mu, _| {
+    ///     mu.root_handle(&Gc::new(mu, Gc::new(mu, 42)))
+    /// });
+    /// ```
+    ///
+    /// The `Gc` must be branded with this mutator's `'gc` lifetime, so a value
+    /// from a different arena cannot be rooted.
+    ///
+    /// ```compile_fail
+    /// # use sandpit::{Arena, Gc, Handle, Root};
+    /// let a: Arena<Root![Gc<'_, Gc<'_, usize>>]> = Arena::new(|mu| Gc::new(mu, Gc::new(mu, 1)));
+    /// let b: Arena<Root![()]> = Arena::new(|_| ());
+    ///
+    /// a.mutate(|_, root| {
+    ///     let handle: Handle<Root![Gc<'_, usize>]> = b.mutate(|mu, _| mu.root_handle(root));
+    /// });
+    /// ```
+    pub fn root_handle<H: ForLt + 'static>(&'gc self, gc: &Gc<'gc, H::Of<'gc>>) -> Handle<H>
+    where
+        for<'a> <H as ForLt>::Of<'a>: Trace,
+    {
+        Handle::new(self.collector, gc)
+    }
+
     /// This fn will return true when a trace is near completion.
     /// The mutation callback should be exited if gc_yield returns true.
     ///
@@ -287,13 +439,128 @@ impl<'gc> Mutator<'gc> {
     /// });
     /// ```
     pub fn gc_yield(&self) -> bool {
+        self.collector.stress_trigger(&self.config.borrow(), StressPoint::Yield);
+        self.collector.pace_mutator(&self.config.borrow());
         self.collector.yield_flag()
     }
 
+    /// Parks the mutator while a collection is waiting on it, allowing memory
+    /// to be freed without exiting the mutation callback. Everything reachable
+    /// from `roots` is kept alive along with the arena's root. Returns true if
+    /// the mutator was parked, or false immediately if no collection is
+    /// waiting on it.
+    ///
+    /// Without the `multi_threaded` feature, which is the default build,
+    /// collections never occur during a mutation, so this does nothing and
+    /// always returns false. Memory is then only freed once the mutation
+    /// exits, so a long running mutation should exit when [`Mutator::gc_yield`]
+    /// returns true instead.
+    ///
+    /// # Safety
+    ///
+    /// If this returns true, any value which was not reachable from `roots`
+    /// or the arena's root may have been freed, and must not be used again.
+    ///
+    /// Every `Gc` created during the mutation shares the mutation's lifetime,
+    /// which cannot end at a safepoint, so the borrow checker would still
+    /// allow the freed values to be used. Taking `roots` only keeps them
+    /// alive, it cannot invalidate the caller's other pointers, which is why
+    /// the caller must uphold this instead.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use sandpit::{Arena, Gc, Root};
+    /// # let arena: Arena<Root![Gc<'_, usize>]> = Arena::new(|mu| {
+    /// #    Gc::new(mu, 123)
+    /// # });
+    /// arena.mutate(|mu, root| {
+    ///     let local = Gc::new(mu, 456);
+    ///
+    ///     for _ in 0..100 {
+    ///         Gc::new(mu, 789);
+    ///
+    ///         // `local` is kept alive, while the rest of the garbage may be freed
+    ///         unsafe { mu.safepoint(&local) };
+    ///     }
+    ///
+    ///     assert_eq!(*local, 456);
+    /// });
+    /// ```
+    pub unsafe fn safepoint<T: Trace>(&self, roots: &T) -> bool {
+        self.collector.pace_mutator(&self.config.borrow());
+
+        if !self.collector.yield_flag() {
+            return false;
+        }
+
+        let work = self.rescan.take();
+        self.collector.send_work(work.into_iter().collect());
+
+        if !self.collector.park_mutator(roots) {
+            return false;
+        }
+
+        // the collection may have rotated the marks, and applied a pending
+        // config
+        self.mark.set(self.collector.prev_mark());
+        self.config.replace(self.collector.config());
+
+        true
+    }
+
+    fn alloc_layout(&self, layout: Layout) -> Result<*const u8, AllocError> {
+        self.check_heap_limit(layout)?;
+
+        let ptr = self.allocator.try_alloc(layout)?;
+
+        if self.config.borrow().verify_heap {
+            self.collector.log_allocation(ptr, layout);
+        }
+
+        self.collector.stress_trigger(&self.config.borrow(), StressPoint::Alloc);
+
+        self.collector
+            .metrics()
+            .allocated_bytes
+            .fetch_add(layout.size() as u64, Ordering::Relaxed);
+
+        Ok(ptr)
+    }
+
+    fn check_heap_limit(&self, layout: Layout) -> Result<(), AllocError> {
+        let metrics = self.collector.metrics();
+        let config = self.config.borrow();
+
+        let Some(max_heap_bytes) = config.max_heap_bytes else {
+            return Ok(());
+        };
+
+        if metrics.estimated_heap_size() + layout.size() as u64 <= max_heap_bytes {
+            return Ok(());
+        }
+
+        self.collector.request_major_collection();
+
+        match config.heap_limit_policy {
+            HeapLimitPolicy::Callback(cb) => {
+                if cb(metrics, layout.size()) {
+                    Ok(())
+                } else {
+                    Err(AllocError::HeapLimitExceeded)
+                }
+            }
+            HeapLimitPolicy::Fail => Err(AllocError::HeapLimitExceeded),
+        }
+    }
+
     pub(crate) fn has_marked<T: Trace + ?Sized>(&self, gc_ptr: &Gc<'gc, T>) -> bool {
         gc_ptr.get_header().get_mark() == self.collector.get_current_mark()
     }
 
+    pub(crate) fn root_set(&self) -> &Arc<RootSet> {
+        self.collector.root_set()
+    }
+
     pub(crate) fn get_mark(&self) -> GcMark {
         self.collector.get_current_mark()
     }
@@ -308,7 +575,7 @@ impl<'gc> Mutator<'gc> {
 
         self.rescan.borrow_mut().insert(trace_job);
 
-        if self.rescan.borrow().len() >= self.collector.config().mutator_share_min {
+        if self.rescan.borrow().len() >= self.config.borrow().mutator_share_min {
             let work = self.rescan.take();
             self.collector.send_work(work.into_iter().collect());
         }
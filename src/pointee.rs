@@ -1,5 +1,6 @@
 use super::trace::Trace;
 use crate::header::{GcHeader, SizedHeader, SliceHeader, StrHeader};
+use crate::heap::AllocError;
 
 use alloc::alloc::Layout;
 use core::marker::PhantomData;
@@ -149,19 +150,31 @@ pub fn sized_alloc_layout<T>() -> (Layout, usize) {
 }
 
 pub fn slice_alloc_layout<T>(len: usize) -> (Layout, usize) {
+    try_slice_alloc_layout::<T>(len).unwrap()
+}
+
+pub fn try_slice_alloc_layout<T>(len: usize) -> Result<(Layout, usize), AllocError> {
     let header_layout = Layout::new::<SliceHeader<T>>();
-    let slice_layout = Layout::array::<T>(len).unwrap();
-    let (unpadded_layout, offset) = header_layout.extend(slice_layout).unwrap();
+    let slice_layout = Layout::array::<T>(len).map_err(|_| AllocError::TooLarge)?;
+    let (unpadded_layout, offset) = header_layout
+        .extend(slice_layout)
+        .map_err(|_| AllocError::TooLarge)?;
     let layout = unpadded_layout.pad_to_align();
 
-    (layout, offset)
+    Ok((layout, offset))
 }
 
 pub fn str_alloc_layout(len: usize) -> (Layout, usize) {
+    try_str_alloc_layout(len).unwrap()
+}
+
+pub fn try_str_alloc_layout(len: usize) -> Result<(Layout, usize), AllocError> {
     let header_layout = Layout::new::<StrHeader>();
-    let str_layout = Layout::array::<u8>(len).unwrap();
-    let (unpadded_layout, offset) = header_layout.extend(str_layout).unwrap();
+    let str_layout = Layout::array::<u8>(len).map_err(|_| AllocError::TooLarge)?;
+    let (unpadded_layout, offset) = header_layout
+        .extend(str_layout)
+        .map_err(|_| AllocError::TooLarge)?;
     let layout = unpadded_layout.pad_to_align();
 
-    (layout, offset)
+    Ok((layout, offset))
 }
@@ -0,0 +1,85 @@
+use crate::config::Config;
+use crate::TriggerMetrics;
+use core::fmt::Debug;
+
+/// The collection a [`CollectionPolicy`] has decided should occur.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CollectionDecision {
+    /// Don't collect.
+    None,
+    /// Perform a minor collection.
+    Minor,
+    /// Perform a major collection.
+    Major,
+}
+
+/// Decides when an arena is collected.
+///
+/// The policy is consulted by the monitor each time it wakes, and in
+/// single-threaded mode at the end of each mutation and whenever a mutator
+/// checks whether it should yield. In either mode, nothing is collected while
+/// the monitor is paused, after which a major collection which has been
+/// explicitly requested, such as by exceeding [`Config::max_heap_bytes`], and
+/// then a collection forced by [`Config::stress_mode`] are performed without
+/// consulting the policy. In single-threaded mode, the policy is then only
+/// consulted if [`Config::monitor_on`] is set.
+///
+/// A policy is set with [`Config::collection_policy`].
+///
+/// ## Example
+/// ```rust
+/// use sandpit::{Arena, CollectionDecision, CollectionPolicy, Config, Root, TriggerMetrics};
+/// use std::sync::Arc;
+///
+/// // Only collect once the arena has doubled in size.
+/// #[derive(Debug)]
+/// struct Doubling;
+///
+/// impl CollectionPolicy for Doubling {
+///     fn decide(&self, metrics: &TriggerMetrics, _config: &Config) -> CollectionDecision {
+///         if metrics.arena_size > metrics.prev_arena_size * 2 {
+///             CollectionDecision::Major
+///         } else {
+///             CollectionDecision::None
+///         }
+///     }
+/// }
+///
+/// let mut config = Config::default();
+/// config.collection_policy = Arc::new(Doubling);
+///
+/// let arena: Arena<Root![()]> = Arena::new_with_config(config, |_| ());
+/// ```
+pub trait CollectionPolicy: Debug + Send + Sync {
+    /// Returns which collection, if any, should occur given a consistent
+    /// view of the arena's current metrics, and its config.
+    fn decide(&self, metrics: &TriggerMetrics, config: &Config) -> CollectionDecision;
+}
+
+/// The policy used by [`Config::default`].
+///
+/// A major collection occurs once the number of old objects surpasses the
+/// max old object count, which is derived from
+/// [`Config::monitor_max_old_growth_rate`]. Otherwise a minor collection
+/// occurs once the arena has grown by
+/// [`Config::monitor_arena_size_ratio_trigger`] since the last collection.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DefaultPolicy;
+
+impl CollectionPolicy for DefaultPolicy {
+    fn decide(&self, metrics: &TriggerMetrics, config: &Config) -> CollectionDecision {
+        if metrics.old_objects_count > metrics.max_old_objects {
+            return CollectionDecision::Major;
+        }
+
+        let arena_size = metrics.arena_size;
+        let prev_arena_size = metrics.prev_arena_size;
+        let arena_size_ratio_trigger = config.monitor_arena_size_ratio_trigger;
+
+        if arena_size as f32 > (prev_arena_size as f32 * arena_size_ratio_trigger) {
+            return CollectionDecision::Minor;
+        }
+
+        CollectionDecision::None
+    }
+}
@@ -0,0 +1,205 @@
+use crate::event::CollectionKind;
+use crate::policy::CollectionDecision;
+use core::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+/// Forces collections far more often than any [`crate::CollectionPolicy`]
+/// would, so that missing write barriers and bad [`crate::Trace`] impls are
+/// found in tests rather than in production. Set with
+/// [`crate::Config::stress_mode`], ideally along with
+/// [`crate::Config::verify_heap`].
+///
+/// Each enabled trigger point requests a collection, alternating between
+/// minor and major collections. A collection cannot free memory while a
+/// mutation is active, so rather than collecting at the trigger point itself,
+/// a collection requested within a mutation makes
+/// [`crate::Mutator::gc_yield`] return true, and runs as soon as the mutation
+/// exits. The requests made within a mutation are coalesced, so each
+/// mutation is followed by at most one stress collection, which is a major
+/// collection if any of the requests were. With the `multi_threaded` feature
+/// it may instead be started sooner by the monitor, if it is on,
+/// concurrently with the mutation.
+///
+/// # Example
+/// ```rust
+/// use sandpit::{Arena, Config, Gc, Root, StressMode};
+///
+/// let mut config = Config::default();
+/// config.monitor_on = false;
+/// config.verify_heap = true;
+/// config.stress_mode = Some(StressMode::random(0x5eed, 4));
+///
+/// let arena: Arena<Root![Gc<'_, usize>]> = Arena::new_with_config(config, |mu| Gc::new(mu, 0));
+///
+/// for i in 0..10 {
+///     arena.mutate(|mu, root| {
+///         Gc::new(mu, i);
+///     });
+/// }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StressMode {
+    /// Request a collection when a value is allocated.
+    pub request_on_alloc: bool,
+    /// Request a collection when [`crate::Mutator::gc_yield`] is called.
+    pub request_on_yield: bool,
+    /// Request a collection when [`crate::Arena::mutate`] is about to return,
+    /// which is then run before it returns.
+    pub request_on_mutate_exit: bool,
+    /// Decides whether each trigger point requests a collection.
+    pub trigger: StressTrigger,
+}
+
+/// Decides which of the trigger points enabled by a [`StressMode`] request a
+/// collection.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StressTrigger {
+    /// Every trigger point requests a collection.
+    Always,
+    /// Each trigger point requests a collection with a probability of 1 in
+    /// `one_in`, and the kind of collection is also chosen at random. The
+    /// choices are made by an RNG seeded with `seed`, so without the
+    /// `multi_threaded` feature, a run which reaches the same trigger points
+    /// in the same order collects at the same points, allowing a failure to
+    /// be reproduced. With it, runs are not reproducible, as mutators on other
+    /// threads and the monitor may reach trigger points or start collections
+    /// in a different order each time.
+    Random { seed: u64, one_in: u32 },
+}
+
+impl StressMode {
+    /// Requests a collection at every trigger point.
+    pub fn always() -> Self {
+        Self {
+            request_on_alloc: true,
+            request_on_yield: true,
+            request_on_mutate_exit: true,
+            trigger: StressTrigger::Always,
+        }
+    }
+
+    /// Requests a collection at 1 in `one_in` trigger points, chosen by an RNG
+    /// seeded with `seed`.
+    pub fn random(seed: u64, one_in: u32) -> Self {
+        Self {
+            trigger: StressTrigger::Random { seed, one_in },
+            ..Self::always()
+        }
+    }
+}
+
+// The points at which a stress collection may be triggered.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StressPoint {
+    Alloc,
+    Yield,
+    MutateExit,
+}
+
+const PENDING_NONE: u8 = 0;
+const PENDING_MINOR: u8 = 1;
+const PENDING_MAJOR: u8 = 2;
+
+// The stress collection which has been requested, along with the state used
+// to decide the next one.
+pub struct StressState {
+    pending: AtomicU8,
+    triggered: AtomicU64,
+    seed: AtomicU64,
+    rng: AtomicU64,
+}
+
+impl StressState {
+    pub fn new() -> Self {
+        Self {
+            pending: AtomicU8::new(PENDING_NONE),
+            triggered: AtomicU64::new(0),
+            seed: AtomicU64::new(0),
+            rng: AtomicU64::new(0),
+        }
+    }
+
+    pub fn trigger(&self, mode: &StressMode, point: StressPoint) {
+        let enabled = match point {
+            StressPoint::Alloc => mode.request_on_alloc,
+            StressPoint::Yield => mode.request_on_yield,
+            StressPoint::MutateExit => mode.request_on_mutate_exit,
+        };
+
+        if !enabled {
+            return;
+        }
+
+        let kind = match mode.trigger {
+            StressTrigger::Always => {
+                if self.triggered.fetch_add(1, Ordering::Relaxed) % 2 == 0 {
+                    CollectionKind::Minor
+                } else {
+                    CollectionKind::Major
+                }
+            }
+            StressTrigger::Random { seed, one_in } => {
+                let random = self.next_random(seed);
+
+                if random % one_in.max(1) as u64 != 0 {
+                    return;
+                }
+
+                if (random >> 32) & 1 == 0 {
+                    CollectionKind::Minor
+                } else {
+                    CollectionKind::Major
+                }
+            }
+        };
+
+        let pending = match kind {
+            CollectionKind::Minor => PENDING_MINOR,
+            CollectionKind::Major => PENDING_MAJOR,
+        };
+
+        // a pending major collection also does the work of a minor one
+        self.pending.fetch_max(pending, Ordering::SeqCst);
+    }
+
+    pub fn pending(&self) -> CollectionDecision {
+        match self.pending.load(Ordering::SeqCst) {
+            PENDING_MINOR => CollectionDecision::Minor,
+            PENDING_MAJOR => CollectionDecision::Major,
+            _ => CollectionDecision::None,
+        }
+    }
+
+    // Called as a collection starts, after which the pending collection has
+    // been done.
+    pub fn collection_started(&self, kind: CollectionKind) {
+        match kind {
+            CollectionKind::Major => self.pending.store(PENDING_NONE, Ordering::SeqCst),
+            CollectionKind::Minor => {
+                let _ = self.pending.compare_exchange(
+                    PENDING_MINOR,
+                    PENDING_NONE,
+                    Ordering::SeqCst,
+                    Ordering::SeqCst,
+                );
+            }
+        }
+    }
+
+    // A splitmix64 generator, which is restarted whenever the seed changes.
+    fn next_random(&self, seed: u64) -> u64 {
+        const GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+        if self.seed.swap(seed, Ordering::Relaxed) != seed {
+            self.rng.store(seed, Ordering::Relaxed);
+        }
+
+        let mut z = self
+            .rng
+            .fetch_add(GAMMA, Ordering::Relaxed)
+            .wrapping_add(GAMMA);
+
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
@@ -0,0 +1,46 @@
+use super::tracer::Tracer;
+use crate::ephemeron::Ephemeron;
+use crate::trace::Trace;
+use core::ptr::NonNull;
+
+unsafe impl Send for EphemeronJob {}
+unsafe impl Sync for EphemeronJob {}
+
+// A type erased pointer to an ephemeron whose key had not yet been marked when
+// the ephemeron was traced.
+pub struct EphemeronJob {
+    ptr: NonNull<()>,
+    dyn_resolve: fn(NonNull<()>, &mut Tracer) -> bool,
+    // the address of the value holding the ephemeron, recorded while a heap
+    // dump is being taken, or `None` if it is held by a root
+    owner: Option<usize>,
+}
+
+impl EphemeronJob {
+    pub fn new<K: Trace + ?Sized, V: Trace + ?Sized>(
+        ephemeron: &Ephemeron<'_, K, V>,
+        owner: Option<usize>,
+    ) -> Self {
+        Self {
+            ptr: NonNull::from(ephemeron).cast(),
+            dyn_resolve: resolve::<K, V>,
+            owner,
+        }
+    }
+
+    pub fn owner(&self) -> Option<usize> {
+        self.owner
+    }
+
+    // Returns true if the ephemeron's key has since been marked, in which case
+    // its value has been traced and the job can be discarded.
+    pub fn resolve(&self, tracer: &mut Tracer) -> bool {
+        (self.dyn_resolve)(self.ptr, tracer)
+    }
+}
+
+fn resolve<K: Trace + ?Sized, V: Trace + ?Sized>(ptr: NonNull<()>, tracer: &mut Tracer) -> bool {
+    let ephemeron: &Ephemeron<'_, K, V> = unsafe { ptr.cast().as_ref() };
+
+    ephemeron.trace_value_if_key_marked(tracer)
+}
@@ -0,0 +1,37 @@
+use crate::pointee::Thin;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicPtr, Ordering};
+use std::collections::HashMap;
+
+// Records the new location of every value moved by a compacting collection,
+// so that any remaining pointers to the old locations can be updated.
+pub struct Forwarding {
+    moved: HashMap<usize, NonNull<Thin<()>>>,
+}
+
+impl Forwarding {
+    pub fn new() -> Self {
+        Self {
+            moved: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, old: NonNull<Thin<()>>, new: NonNull<Thin<()>>) {
+        self.moved.insert(old.as_ptr() as usize, new);
+    }
+
+    pub fn get(&self, old: usize) -> Option<NonNull<Thin<()>>> {
+        self.moved.get(&old).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.moved.len()
+    }
+
+    // Updates the pointer if the value it points at was moved.
+    pub fn forward<T: ?Sized>(&self, ptr: &AtomicPtr<Thin<T>>) {
+        if let Some(new) = self.get(ptr.load(Ordering::SeqCst) as usize) {
+            ptr.store(new.cast().as_ptr(), Ordering::SeqCst);
+        }
+    }
+}
@@ -1,13 +1,25 @@
 mod collector;
+mod ephemeron_job;
+mod forwarding;
 #[cfg(feature = "multi_threaded")]
 pub mod multi_threaded_collector;
+mod occupancy;
 #[cfg(not(feature = "multi_threaded"))]
 mod single_threaded_collector;
+#[cfg(feature = "multi_threaded")]
+mod sweeper;
+#[cfg(feature = "multi_threaded")]
+mod timeslicer;
 mod trace;
 mod trace_job;
 mod tracer;
+#[cfg(feature = "multi_threaded")]
+mod tracer_pool;
+mod weak_job;
 
 pub use collector::Collector;
+pub use forwarding::Forwarding;
 pub use trace::{Trace, TraceLeaf, __MustNotDrop};
 pub use trace_job::TraceJob;
 pub use tracer::Tracer;
+pub use weak_job::WeakRef;
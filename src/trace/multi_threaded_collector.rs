@@ -1,67 +1,165 @@
+use super::ephemeron_job::EphemeronJob;
+use super::occupancy::Occupancy;
+use super::sweeper::Sweeper;
+use super::timeslicer::Timeslicer;
 use super::trace::Trace;
 use super::trace_job::TraceJob;
 use super::tracer::Tracer;
-use crate::config::Config;
+use super::tracer_pool::TracerPool;
+use super::weak_job::WeakJob;
+use crate::census::{CensusRecorder, HeapCensus};
+use crate::config::{Config, ConfigError};
 use crate::debug::gc_debug;
+use crate::event::{CollectionKind, EventListeners, GcEvent, GcEventListener};
+use crate::finalize::FinalizeJob;
+use crate::handle::RootSet;
 use crate::header::GcMark;
 use crate::heap::{Allocator, Heap};
+use crate::heap_dump::{DumpRecorder, HeapDump};
 use crate::metrics::{
     GC_STATE_SLEEPING, GC_STATE_SWEEPING, GC_STATE_TRACING, GC_STATE_WAITING_ON_MUTATORS,
 };
 use crate::pointee::Thin;
+use crate::policy::CollectionDecision;
+use crate::stress::{StressPoint, StressState};
+use crate::verify::AllocationLog;
 use crate::Metrics;
+use alloc::alloc::Layout;
 use alloc::format;
+use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::Vec;
+use core::cell::RefCell;
 use core::ptr::NonNull;
-use core::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
-use crossbeam_channel::{Receiver, Sender};
-use std::sync::Mutex;
-use std::time::{Instant, SystemTime};
+use core::sync::atomic::{fence, AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use crossbeam_deque::{Injector, Steal, Stealer, Worker};
+use std::sync::{Condvar, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+std::thread_local! {
+    // The deque of work belonging to the tracer running on this thread. Only
+    // threads in a collector's tracer pool have one, work shared from any
+    // other thread is pushed into the collector's injector instead.
+    static LOCAL_WORK: RefCell<Option<Worker<Vec<TraceJob>>>> = const { RefCell::new(None) };
+}
 
 pub struct MultiThreadedCollector {
-    sender: Sender<Vec<TraceJob>>,
-    receiver: Receiver<Vec<TraceJob>>,
-    heap: Heap,
+    injector: Injector<Vec<TraceJob>>,
+    stealers: RwLock<Vec<Stealer<Vec<TraceJob>>>>,
+    tracer_pool: Mutex<TracerPool>,
+    tracers: AtomicUsize,
+    idle_tracers: AtomicUsize,
+    // idle tracers wait on this until work is shared, the last mutator stops,
+    // or the trace completes
+    idle_lock: Mutex<()>,
+    idle_signal: Condvar,
+    finalizers: Mutex<Vec<FinalizeJob>>,
+    weak_refs: Mutex<Vec<WeakJob>>,
+    ephemerons: Mutex<Vec<EphemeronJob>>,
+    roots: Arc<RootSet>,
+    heap: Arc<Heap>,
+    sweeper: Mutex<Option<Sweeper>>,
+    timeslicer: Timeslicer,
     current_mark: AtomicU8,
     yield_flag: AtomicBool,
+    major_requested: AtomicBool,
     collection_lock: Mutex<()>,
     active_mutators: AtomicUsize,
-    shutdown_flag: AtomicBool,
-    pub config: Config,
-    pub metrics: Metrics,
+    parked_mutators: AtomicUsize,
+    monitor_stop_flag: AtomicBool,
+    monitor_paused: AtomicUsize,
+    config: RwLock<Config>,
+    // an update to the config which will be applied at the next collection
+    // boundary
+    pending_config: Mutex<Option<Config>>,
+    pub metrics: Arc<Metrics>,
+    events: Arc<EventListeners>,
+    // set while a major collection is taking a census of the heap
+    census: Mutex<Option<CensusRecorder>>,
+    // set while a major collection is recording the object graph
+    dump: Mutex<Option<DumpRecorder>>,
+    // set while a major collection is tracing ahead of a compaction
+    occupancy: Mutex<Option<Occupancy>>,
+    // held for reading by views, which hold references to values that must
+    // not be moved, and for writing by compaction
+    view_lock: RwLock<()>,
+    // the allocations made while heap verification is enabled
+    allocations: AllocationLog,
+    // the collection requested by the stress mode, if any
+    stress: StressState,
 }
 
 impl MultiThreadedCollector {
     pub fn new(config: Config) -> Self {
-        let (sender, receiver) = crossbeam_channel::unbounded();
-        let heap = Heap::new();
-        let metrics = Metrics::new();
+        let heap = Arc::new(Heap::new());
+        let metrics = Arc::new(Metrics::new());
+        let events = Arc::new(EventListeners::new());
+        let sweeper = config
+            .concurrent_sweep
+            .then(|| Sweeper::new(heap.clone(), metrics.clone(), events.clone()));
+        let tracer_pool = TracerPool::new(config.tracer_threads);
 
         Self {
             heap,
-            sender,
-            receiver,
+            sweeper: Mutex::new(sweeper),
+            timeslicer: Timeslicer::new(),
+            injector: Injector::new(),
+            stealers: RwLock::new(Vec::new()),
+            tracer_pool: Mutex::new(tracer_pool),
+            tracers: AtomicUsize::new(0),
+            idle_tracers: AtomicUsize::new(0),
+            idle_lock: Mutex::new(()),
+            idle_signal: Condvar::new(),
+            finalizers: Mutex::new(Vec::new()),
+            weak_refs: Mutex::new(Vec::new()),
+            ephemerons: Mutex::new(Vec::new()),
+            roots: Arc::new(RootSet::new()),
             yield_flag: AtomicBool::new(false),
+            major_requested: AtomicBool::new(false),
             collection_lock: Mutex::new(()),
             active_mutators: AtomicUsize::new(0),
+            parked_mutators: AtomicUsize::new(0),
             current_mark: AtomicU8::new(GcMark::Red.into()),
-            shutdown_flag: AtomicBool::new(false),
+            monitor_stop_flag: AtomicBool::new(false),
+            monitor_paused: AtomicUsize::new(0),
             metrics,
-            config,
+            events,
+            census: Mutex::new(None),
+            dump: Mutex::new(None),
+            occupancy: Mutex::new(None),
+            view_lock: RwLock::new(()),
+            allocations: AllocationLog::new(),
+            stress: StressState::new(),
+            config: RwLock::new(config),
+            pending_config: Mutex::new(None),
         }
     }
 
-    pub fn shutdown(&self) {
-        self.shutdown_flag.store(true, Ordering::SeqCst);
+    // Signals the monitor thread to exit after it next wakes.
+    pub fn stop_monitor(&self) {
+        self.monitor_stop_flag.store(true, Ordering::SeqCst);
+    }
+
+    pub fn should_stop_monitor(&self) -> bool {
+        self.monitor_stop_flag.load(Ordering::SeqCst)
+    }
+
+    // While paused the monitor keeps running, but no collections are
+    // triggered. Pauses nest, each must be matched by a resume.
+    pub fn pause_monitor(&self) {
+        self.monitor_paused.fetch_add(1, Ordering::SeqCst);
     }
 
-    pub fn should_shutdown(&self) -> bool {
-        self.shutdown_flag.load(Ordering::SeqCst)
+    pub fn resume_monitor(&self) {
+        let _ = self
+            .monitor_paused
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |paused| {
+                paused.checked_sub(1)
+            });
     }
 
-    pub fn check_yield(&self) {
-        let max_headroom = (1.0 + self.config.collector_max_headroom_ratio) * self.metrics.prev_arena_size.load(Ordering::Relaxed) as f64;
+    pub fn check_yield(&self, config: &Config) {
+        let max_headroom = (1.0 + config.collector_max_headroom_ratio) * self.metrics.prev_arena_size.load(Ordering::Relaxed) as f64;
 
         if self.metrics.arena_size.load(Ordering::Relaxed) as f64 > max_headroom {
             self.raise_yield_flag()
@@ -69,48 +167,82 @@ impl MultiThreadedCollector {
     }
 
     /// Spawn a monitor thread for automatic garbage collection.
-    /// Returns Some(JoinHandle) if monitor_on is true in config, None otherwise.
     ///
     /// # Safety
     /// The caller must ensure that root_ptr remains valid for the lifetime of the monitor thread.
     pub fn spawn_monitor_thread<T: Trace + 'static>(
         self: alloc::sync::Arc<Self>,
         root_ptr: *const T,
-    ) -> Option<std::thread::JoinHandle<()>> {
-        if !self.config.monitor_on {
-            return None;
-        }
+    ) -> std::thread::JoinHandle<()> {
+        self.monitor_stop_flag.store(false, Ordering::SeqCst);
 
         // Wrap pointer to make it Send (usize is Send)
         let root_ptr_addr = root_ptr as usize;
 
-        let handle = std::thread::spawn(move || {
+        std::thread::spawn(move || {
             // SAFETY: We're reconstructing the pointer that was valid when passed in
             let root_ptr = root_ptr_addr as *const T;
             monitor::spawn_monitor(self, root_ptr);
-        });
+        })
+    }
 
-        Some(handle)
+    // Returns the duration of the collection, and of its pause, which begins
+    // at the instant returned by `f`.
+    fn timed_collection(&self, f: impl FnOnce() -> Instant) -> (Duration, Duration) {
+        let start_time = Instant::now();
+        let pause_start = f();
+
+        (start_time.elapsed(), pause_start.elapsed())
     }
 
-    fn timed_collection(&self, is_major: bool, f: impl FnOnce()) {
-        let start_time = SystemTime::now();
+    // Emits the start of a collection, returning the arena size and old
+    // object count needed to describe the collection once it has finished.
+    fn collection_started(&self, kind: CollectionKind) -> (u64, u64) {
+        let arena_size = self.get_arena_size();
 
-        f();
+        self.stress.collection_started(kind);
+        self.events.emit(GcEvent::CollectionStarted { kind, arena_size });
 
-        let collection_duration: u64 = start_time.elapsed().unwrap().as_millis() as u64;
+        (arena_size, self.metrics.get_old_objects_count())
+    }
 
-        if is_major {
-            self.metrics
-                .update_major_collection_avg_time(collection_duration);
-        } else {
-            self.metrics
-                .update_minor_collection_avg_time(collection_duration);
-        }
+    fn collection_finished(&self, kind: CollectionKind, started: (u64, u64), duration: Duration) {
+        let (bytes_before, old_objects_before) = started;
+
+        self.events.emit(GcEvent::CollectionFinished {
+            kind,
+            duration,
+            bytes_before,
+            bytes_after: self.metrics.get_arena_size(),
+            objects_marked: self.metrics.get_old_objects_count().saturating_sub(old_objects_before),
+        });
+    }
+
+    pub fn add_event_listener(&self, listener: Arc<dyn GcEventListener>) {
+        self.events.add(listener);
     }
 
-    fn trace_and_sweep<T: Trace + ?Sized>(&self, root: &T) {
+    // Returns when the trace completed, after which no mutator can run until
+    // the collection has finished.
+    fn trace_and_sweep<T: Trace + ?Sized>(&self, root: &T) -> Instant {
+        let allocated_bytes = self.metrics.get_allocated_bytes();
         self.trace(root);
+        let pause_start = Instant::now();
+        self.clear_weak_refs();
+        self.finalize_unmarked();
+
+        // Values declared at a safepoint are not forwarded, so they must not
+        // be moved while their mutator is parked, and neither may values
+        // referenced by a view.
+        if let Some(occupancy) = self.occupancy.lock().unwrap().take() {
+            if self.parked_mutators.load(Ordering::SeqCst) == 0 {
+                if let Ok(_views) = self.view_lock.try_write() {
+                    self.compact(root, occupancy);
+                }
+            }
+        }
+
+        self.verify_heap(root);
 
         self.metrics
             .state
@@ -119,11 +251,22 @@ impl MultiThreadedCollector {
         // have dropped their yield locks, ensuring no mutation contexts exist
         // and we hold the collection lock, ensuring no mutation contexts can
         // be created at this point
+        //
+        // A concurrent sweep only touches blocks which are not held by any
+        // allocator, so mutation contexts may be created while it runs, and
+        // the next collection waits for it before tracing.
         unsafe {
             self.sweep();
         }
+        // values allocated while tracing are not included in the size of the
+        // arena before the collection, so they are still counted afterwards
+        self.metrics
+            .allocated_bytes
+            .fetch_sub(allocated_bytes, Ordering::Relaxed);
 
         self.print_debug_info();
+
+        pause_start
     }
 
     fn trace<T: Trace + ?Sized>(&self, root: &T) {
@@ -131,8 +274,11 @@ impl MultiThreadedCollector {
         self.metrics
             .state
             .store(GC_STATE_TRACING, Ordering::Relaxed);
+        self.timeslicer.start_trace(&self.metrics);
         self.trace_root(root);
         self.spawn_tracers();
+        self.trace_ephemerons();
+        self.timeslicer.finish_trace();
         self.clean_up();
         gc_debug("Trace Complete!");
     }
@@ -140,57 +286,168 @@ impl MultiThreadedCollector {
     fn trace_root<T: Trace + ?Sized>(&self, root: &T) {
         let ptr: NonNull<Thin<T>> = NonNull::from(root).cast();
         let trace_job = TraceJob::new(ptr);
-        self.sender.send(vec![trace_job]).unwrap();
+        self.injector.push(vec![trace_job]);
+        self.injector.push(self.roots.jobs());
     }
 
     fn spawn_tracers(&self) {
-        std::thread::scope(|scope| {
-            let mut handles = vec![];
+        let tracer_pool = self.tracer_pool.lock().unwrap();
 
-            for _ in 0..self.config.tracer_threads {
-                let h = scope.spawn(|| {
-                    gc_debug("Tracer Spawned");
-                    self.run_tracer();
-                });
+        self.start_tracers(tracer_pool.threads());
 
-                handles.push(h);
-            }
+        if tracer_pool.run(&|| self.run_tracer()).is_err() {
+            println!("thread panicked, shutting down process");
+            std::process::exit(1)
+        }
+    }
 
-            for h in handles.into_iter() {
-                if h.join().is_err() {
-                    println!("thread panicked, shutting down process");
-                    std::process::exit(1)
-                }
+    fn run_tracer(&self) {
+        gc_debug("Tracer Started");
+        self.register_local_work();
+        self.finish_tracer(self.new_tracer());
+    }
+
+    // Gives the current tracer pool thread its own deque, the first time it
+    // runs a tracer.
+    fn register_local_work(&self) {
+        LOCAL_WORK.with(|local| {
+            let mut local = local.borrow_mut();
+
+            if local.is_none() {
+                let worker = Worker::new_lifo();
+
+                self.stealers.write().unwrap().push(worker.stealer());
+                *local = Some(worker);
             }
         });
     }
 
-    fn run_tracer(&self) {
-        let mut tracer = self.new_tracer();
+    fn start_tracers(&self, tracers: usize) {
+        self.tracers.store(tracers, Ordering::SeqCst);
+        self.idle_tracers.store(0, Ordering::SeqCst);
+    }
+
+    fn finish_tracer(&self, mut tracer: Tracer<'_>) {
         let marked_objects = tracer.trace_loop() as u64;
+        self.weak_refs
+            .lock()
+            .unwrap()
+            .append(&mut tracer.take_weak());
+        self.ephemerons
+            .lock()
+            .unwrap()
+            .append(&mut tracer.take_ephemerons());
         self.metrics
             .old_objects_count
             .fetch_add(marked_objects, Ordering::SeqCst);
+
+        if let Some(census) = tracer.take_census() {
+            if let Some(total) = self.census.lock().unwrap().as_mut() {
+                total.merge(census);
+            }
+        }
+
+        if let Some(dump) = tracer.take_dump() {
+            if let Some(total) = self.dump.lock().unwrap().as_mut() {
+                total.merge(dump);
+            }
+        }
+
+        if let Some(occupancy) = tracer.take_occupancy() {
+            if let Some(total) = self.occupancy.lock().unwrap().as_mut() {
+                total.merge(occupancy);
+            }
+        }
+    }
+
+    // A tracer may exit with deferred ephemerons whose keys were later marked
+    // by another tracer. Once every tracer has exited, a single tracer
+    // rechecks them until no more keys are found to be marked.
+    fn trace_ephemerons(&self) {
+        let ephemerons = core::mem::take(&mut *self.ephemerons.lock().unwrap());
+
+        if ephemerons.is_empty() {
+            return;
+        }
+
+        self.start_tracers(1);
+
+        let mut tracer = self.new_tracer();
+        tracer.add_ephemerons(ephemerons);
+        self.finish_tracer(tracer);
+        self.ephemerons.lock().unwrap().clear();
     }
 
     fn new_tracer(&self) -> Tracer<'_> {
         let mark = self.get_current_mark();
-        Tracer::new(self, mark)
+        let mut tracer = Tracer::new(self, mark);
+
+        if self.census.lock().unwrap().is_some() {
+            tracer.enable_census();
+        }
+
+        if self.dump.lock().unwrap().is_some() {
+            tracer.enable_dump();
+        }
+
+        if self.occupancy.lock().unwrap().is_some() {
+            tracer.enable_occupancy();
+        }
+
+        tracer
     }
 
+    // The trace is complete once every tracer is idle, all mutators have
+    // stopped, and no work is left to be stolen. Mutators share their
+    // remaining work before they stop, so it must be checked that they have
+    // stopped before checking for work.
     fn is_trace_completed(&self) -> bool {
-        if self.receiver.is_empty() {
-            if self.mutators_stopped() {
-                return true;
+        if !self.mutators_stopped() {
+            let prev_state = self
+                .metrics
+                .state
+                .swap(GC_STATE_WAITING_ON_MUTATORS, Ordering::Relaxed);
+
+            if prev_state != GC_STATE_WAITING_ON_MUTATORS {
+                self.events.emit(GcEvent::WaitingOnMutators {
+                    active_mutators: self.active_mutators.load(Ordering::SeqCst),
+                });
             }
 
-            self.metrics
-                .state
-                .store(GC_STATE_WAITING_ON_MUTATORS, Ordering::Relaxed);
             self.raise_yield_flag();
+
+            return false;
         }
 
-        false
+        self.idle_tracers.load(Ordering::SeqCst) >= self.tracers.load(Ordering::SeqCst)
+            && !self.has_work()
+    }
+
+    // Takes work from this thread's own deque, or else steals it from the
+    // injector or another tracer.
+    fn find_work(&self) -> Option<Vec<TraceJob>> {
+        let local_work = LOCAL_WORK.with(|local| local.borrow().as_ref().and_then(Worker::pop));
+
+        if local_work.is_some() {
+            return local_work;
+        }
+
+        loop {
+            let steal = self.injector.steal().or_else(|| {
+                self.stealers
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .map(Stealer::steal)
+                    .collect()
+            });
+
+            match steal {
+                Steal::Success(work) => return Some(work),
+                Steal::Empty => return None,
+                Steal::Retry => continue,
+            }
+        }
     }
 
     fn clean_up(&self) {
@@ -198,15 +455,148 @@ impl MultiThreadedCollector {
     }
 
     fn raise_yield_flag(&self) {
-        self.yield_flag.store(true, Ordering::SeqCst);
+        if !self.yield_flag.swap(true, Ordering::SeqCst) {
+            self.events.emit(GcEvent::YieldRequested);
+        }
     }
 
     fn mutators_stopped(&self) -> bool {
         self.active_mutators.load(Ordering::SeqCst) == 0
     }
 
+    fn clear_weak_refs(&self) {
+        let mark = self.get_current_mark();
+        let weak_refs = core::mem::take(&mut *self.weak_refs.lock().unwrap());
+
+        for job in weak_refs {
+            // SAFETY: the trace is complete and no sweep has occurred since
+            // the weak reference was recorded.
+            unsafe { job.clear_if_unmarked(mark) }
+        }
+    }
+
+    // Moves the values reachable from the root out of sparsely occupied
+    // blocks into newly allocated space, so that the sweep which follows
+    // frees the space they were moved out of. This requires a completed
+    // trace, and that no mutators or views exist.
+    fn compact<T: Trace + ?Sized>(&self, root: &T, occupancy: Occupancy) {
+        gc_debug("Compacting...");
+
+        let mark = self.rotate_mark();
+        let ptr: NonNull<Thin<T>> = NonNull::from(root).cast();
+        let mut roots = self.roots.jobs();
+        roots.push(TraceJob::new(ptr));
+
+        let allocator = Allocator::from(&*self.heap);
+        let mut tracer = Tracer::new_compacting(self, mark, allocator, occupancy.candidates());
+        let forwarding = tracer.compact(roots);
+        let mut weak_refs = tracer.take_weak();
+        // the tracer's allocator must be dropped before sweeping
+        drop(tracer);
+
+        gc_debug(&format!("Moved {} objects", forwarding.len()));
+
+        self.roots.forward(&forwarding);
+
+        for job in self.finalizers.lock().unwrap().iter_mut() {
+            job.forward(&forwarding);
+        }
+
+        for job in weak_refs.iter() {
+            // SAFETY: the weak references were reached by the compacting
+            // trace, and no sweep has occurred since.
+            unsafe { job.forward(&forwarding) }
+        }
+
+        self.weak_refs.lock().unwrap().append(&mut weak_refs);
+
+        // Values marked by the preceding trace which are no longer reachable
+        // were not moved, so they must be treated as unreachable as well.
+        self.clear_weak_refs();
+        self.finalize_unmarked();
+    }
+
+    // Checks that every value reachable from the roots has been marked, then
+    // poisons the values which are about to be freed.
+    fn verify_heap<T: Trace + ?Sized>(&self, root: &T) {
+        if !self.config().verify_heap {
+            self.allocations.clear();
+            return;
+        }
+
+        let mut roots = self.roots.jobs();
+        roots.push(TraceJob::new(NonNull::from(root).cast::<Thin<T>>()));
+
+        let mark = self.get_current_mark();
+        Tracer::new_verifying(self, mark).verify(roots);
+
+        // SAFETY: the trace has completed and the sweep has not yet begun.
+        // Every logged allocation begins with a header.
+        unsafe { self.allocations.poison_unmarked(mark) };
+    }
+
+    pub fn log_allocation(&self, ptr: *const u8, layout: Layout) {
+        self.allocations.record(ptr, layout);
+    }
+
+    fn finalize_unmarked(&self) {
+        let mark = self.get_current_mark();
+        let mut finalizers = self.finalizers.lock().unwrap();
+        let (live, dead): (Vec<FinalizeJob>, Vec<FinalizeJob>) = core::mem::take(&mut *finalizers)
+            .into_iter()
+            .partition(|job| job.is_marked(mark));
+
+        *finalizers = live;
+        drop(finalizers);
+
+        gc_debug(&format!("Finalizing {} objects", dead.len()));
+
+        for job in dead {
+            // SAFETY: the trace is complete and the value was not marked,
+            // meaning it is unreachable, and it has not been swept yet.
+            unsafe { job.finalize() }
+        }
+    }
+
     unsafe fn sweep(&self) {
+        if let Some(sweeper) = self.sweeper.lock().unwrap().as_ref() {
+            sweeper.sweep(self.get_current_mark());
+            return;
+        }
+
+        let start_time = Instant::now();
+
         self.heap.sweep(self.get_current_mark());
+
+        self.events.emit(GcEvent::SweepFinished {
+            duration: start_time.elapsed(),
+            arena_size: self.get_arena_size(),
+        });
+    }
+
+    // A concurrent sweep records the arena size itself once it has finished,
+    // as the arena cannot be measured until then.
+    fn record_arena_size(&self) {
+        let sweeping = self
+            .sweeper
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(Sweeper::is_sweeping);
+
+        if !sweeping {
+            self.metrics
+                .prev_arena_size
+                .store(self.get_arena_size(), Ordering::Relaxed);
+        }
+    }
+
+    // A concurrent sweep from the previous collection must finish before
+    // the heap is marked again.
+    fn wait_for_sweep(&self) {
+        if let Some(sweeper) = self.sweeper.lock().unwrap().as_ref() {
+            sweeper.wait();
+        }
     }
 
     fn print_debug_info(&self) {
@@ -233,27 +623,94 @@ impl MultiThreadedCollector {
     pub fn major_collect<T: Trace + ?Sized>(&self, root: &T) {
         let _guard = self.collection_lock.lock().unwrap();
 
+        self.major_collect_locked(root);
+    }
+
+    // Runs a major collection which records the type and size of every value
+    // it marks.
+    pub fn heap_census<T: Trace + ?Sized>(&self, root: &T) -> HeapCensus {
+        let _guard = self.collection_lock.lock().unwrap();
+
+        *self.census.lock().unwrap() = Some(CensusRecorder::default());
+        self.major_collect_locked(root);
+
+        self.census
+            .lock()
+            .unwrap()
+            .take()
+            .unwrap_or_default()
+            .finish()
+    }
+
+    // Runs a major collection which records every value it marks and every
+    // pointer it traces. Compaction is skipped so that the recorded
+    // addresses remain valid.
+    pub fn heap_dump<T: Trace + ?Sized>(&self, root: &T) -> HeapDump {
+        let _guard = self.collection_lock.lock().unwrap();
+
+        *self.dump.lock().unwrap() = Some(DumpRecorder::default());
+        self.major_collect_locked(root);
+
+        self.dump
+            .lock()
+            .unwrap()
+            .take()
+            .unwrap_or_default()
+            .finish()
+    }
+
+    // Runs a major collection unless it would have to wait for another
+    // collection or for mutators to exit, returning whether it ran.
+    pub fn try_major_collect<T: Trace + ?Sized>(&self, root: &T) -> bool {
+        let Ok(_guard) = self.collection_lock.try_lock() else {
+            return false;
+        };
+
+        // mutators cannot be created while the collection lock is held
+        if !self.mutators_stopped() {
+            return false;
+        }
+
+        self.major_collect_locked(root);
+
+        true
+    }
+
+    fn major_collect_locked<T: Trace + ?Sized>(&self, root: &T) {
         gc_debug("Starting Major Collection");
 
+        self.apply_pending_config();
+        self.wait_for_sweep();
+
+        self.major_requested.store(false, Ordering::SeqCst);
+
         self.metrics.old_objects_count.store(0, Ordering::Relaxed);
+        let started = self.collection_started(CollectionKind::Major);
         self.rotate_mark();
-        self.timed_collection(true, || self.trace_and_sweep(root));
 
-        self.metrics
-            .major_collections
-            .fetch_add(1, Ordering::Relaxed);
-        self.metrics
-            .prev_arena_size
-            .store(self.get_arena_size(), Ordering::Relaxed);
+        if self.config().compaction_on && self.dump.lock().unwrap().is_none() {
+            *self.occupancy.lock().unwrap() = Some(Occupancy::default());
+        }
+
+        let (duration, pause) = self.timed_collection(|| self.trace_and_sweep(root));
+
+        self.metrics.begin_update();
+        self.metrics.record_major_collection(duration, pause);
+        self.record_arena_size();
         let old_objects = self.metrics.get_old_objects_count();
         self.metrics.max_old_objects.store(
-            (old_objects as f32 * self.config.monitor_max_old_growth_rate).floor() as u64,
+            (old_objects as f32 * self.config().monitor_max_old_growth_rate).floor() as u64,
             Ordering::Relaxed,
         );
+        self.metrics.end_update();
 
         self.metrics
             .state
             .store(GC_STATE_SLEEPING, Ordering::Relaxed);
+
+        self.collection_finished(CollectionKind::Major, started, duration);
+
+        self.apply_pending_config();
     }
 
     pub fn minor_collect<T: Trace + ?Sized>(&self, root: &T) {
@@ -261,18 +718,24 @@ impl MultiThreadedCollector {
 
         gc_debug("Starting Minor Collection");
 
-        self.timed_collection(false, || self.trace_and_sweep(root));
+        self.apply_pending_config();
+        self.wait_for_sweep();
 
-        self.metrics
-            .minor_collections
-            .fetch_add(1, Ordering::Relaxed);
-        self.metrics
-            .prev_arena_size
-            .store(self.get_arena_size(), Ordering::Relaxed);
+        let started = self.collection_started(CollectionKind::Minor);
+        let (duration, pause) = self.timed_collection(|| self.trace_and_sweep(root));
+
+        self.metrics.begin_update();
+        self.metrics.record_minor_collection(duration, pause);
+        self.record_arena_size();
+        self.metrics.end_update();
 
         self.metrics
             .state
             .store(GC_STATE_SLEEPING, Ordering::Relaxed);
+
+        self.collection_finished(CollectionKind::Minor, started, duration);
+
+        self.apply_pending_config();
     }
 
     pub fn get_current_mark(&self) -> GcMark {
@@ -291,37 +754,130 @@ impl MultiThreadedCollector {
 
     pub fn new_allocator(&self) -> Allocator {
         let _lock = self.collection_lock.lock();
-        Allocator::from(&self.heap)
+        Allocator::from(&*self.heap)
+    }
+
+    pub fn root_set(&self) -> &Arc<RootSet> {
+        &self.roots
+    }
+
+    pub fn request_major_collection(&self) {
+        self.major_requested.store(true, Ordering::SeqCst);
+        self.raise_yield_flag();
+    }
+
+    pub fn is_major_requested(&self) -> bool {
+        self.major_requested.load(Ordering::SeqCst)
+    }
+
+    // Runs `f` while preventing values from being moved, waiting for any
+    // ongoing compaction to finish first.
+    pub fn view<R>(&self, f: impl FnOnce() -> R) -> R {
+        let _guard = self.view_lock.read().unwrap();
+
+        f()
+    }
+
+    pub fn register_finalizer(&self, job: FinalizeJob) {
+        self.finalizers.lock().unwrap().push(job);
     }
 
     pub fn send_work(&self, work: Vec<TraceJob>) {
-        self.sender.send(work).unwrap();
+        let work = LOCAL_WORK.with(|local| match local.borrow().as_ref() {
+            Some(worker) => {
+                worker.push(work);
+                None
+            }
+            None => Some(work),
+        });
+
+        if let Some(work) = work {
+            self.injector.push(work);
+        }
+
+        self.wake_tracers();
     }
 
+    // Returns None once the trace is complete. A tracer that finds no work is
+    // counted as idle, and waits until it finds work again.
     pub fn recv_work(&self) -> Option<Vec<TraceJob>> {
-        let duration = core::time::Duration::from_millis(self.config.trace_wait_time);
-        let deadline = Instant::now().checked_add(duration).unwrap();
+        if let Some(work) = self.find_work() {
+            return Some(work);
+        }
+
+        self.idle_tracers.fetch_add(1, Ordering::SeqCst);
+        let mut guard = self.idle_lock.lock().unwrap();
 
         loop {
-            match self.receiver.recv_deadline(deadline) {
-                Ok(work) => {
+            // pairs with the fence in wake_tracers, so that either this
+            // tracer sees the shared work, or it is seen to be idle and woken
+            fence(Ordering::SeqCst);
+
+            if self.has_work() {
+                drop(guard);
+                self.idle_tracers.fetch_sub(1, Ordering::SeqCst);
+
+                if let Some(work) = self.find_work() {
                     return Some(work);
                 }
-                Err(_) => {
-                    if self.is_trace_completed() {
-                        return None;
-                    }
-                }
+
+                self.idle_tracers.fetch_add(1, Ordering::SeqCst);
+                guard = self.idle_lock.lock().unwrap();
+                continue;
             }
+
+            if self.is_trace_completed() {
+                self.idle_signal.notify_all();
+                return None;
+            }
+
+            guard = if self.mutators_stopped() {
+                self.idle_signal.wait(guard).unwrap()
+            } else {
+                let timeout = Duration::from_millis(self.config().trace_wait_time);
+                self.idle_signal.wait_timeout(guard, timeout).unwrap().0
+            };
+        }
+    }
+
+    // Wakes the idle tracers to check for work, or for whether the trace has
+    // completed.
+    fn wake_tracers(&self) {
+        fence(Ordering::SeqCst);
+
+        if self.idle_tracers.load(Ordering::SeqCst) > 0 {
+            let _guard = self.idle_lock.lock().unwrap();
+            self.idle_signal.notify_all();
         }
     }
 
     pub fn has_work(&self) -> bool {
-        !self.receiver.is_empty()
+        !self.injector.is_empty()
+            || self
+                .stealers
+                .read()
+                .unwrap()
+                .iter()
+                .any(|stealer| !stealer.is_empty())
+    }
+
+    // Applies back-pressure to a mutator while a trace is in progress. Once
+    // the trace is waiting on mutators they are instead expected to exit.
+    pub fn pace_mutator(&self, config: &Config) {
+        if self.metrics.get_state() != GC_STATE_TRACING {
+            return;
+        }
+
+        self.timeslicer.pace(config, &self.metrics);
+    }
+
+    // Counts the values marked by a tracer towards the progress of the trace.
+    pub fn record_marks(&self, marks: usize) {
+        self.timeslicer.record_marks(marks);
     }
 
     pub fn yield_flag(&self) -> bool {
-        self.yield_flag.load(Ordering::SeqCst)
+        self.yield_flag.load(Ordering::SeqCst) || self.stress_pending() != CollectionDecision::None
     }
 
     pub fn increment_mutators(&self) {
@@ -331,30 +887,133 @@ impl MultiThreadedCollector {
     }
 
     pub fn decrement_mutators(&self) {
-        self.active_mutators.fetch_sub(1, Ordering::SeqCst);
+        if self.active_mutators.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.wake_tracers();
+        }
     }
 
-    pub fn major_trigger(&self) -> bool {
-        let current_old_objects_count = self.metrics.old_objects_count.load(Ordering::Relaxed);
-        let max_old_objects_count = self.metrics.max_old_objects.load(Ordering::Relaxed);
+    // Parks a mutator until the ongoing collection has completed, with
+    // `roots` traced as additional roots. Returns false without parking if no
+    // trace is in progress.
+    //
+    // The roots are traced in place, which is sound b/c the trace cannot
+    // complete until this mutator has stopped, and the mutator is not resumed
+    // until the collection has completed.
+    pub fn park_mutator<T: Trace>(&self, roots: &T) -> bool {
+        if !self.is_tracing() {
+            return false;
+        }
+
+        let ptr: NonNull<Thin<T>> = NonNull::from(roots).cast();
 
-        current_old_objects_count > max_old_objects_count
+        self.parked_mutators.fetch_add(1, Ordering::SeqCst);
+        self.send_work(vec![TraceJob::new(ptr)]);
+        self.decrement_mutators();
+        // blocks until the collection holding the lock has completed
+        self.increment_mutators();
+        self.parked_mutators.fetch_sub(1, Ordering::SeqCst);
+
+        true
     }
 
-    pub fn minor_trigger(&self) -> bool {
-        let arena_size = self.get_arena_size();
-        let prev_arena_size = self.metrics.prev_arena_size.load(Ordering::Relaxed);
-        let arena_size_ratio_trigger = self.config.monitor_arena_size_ratio_trigger;
+    fn is_tracing(&self) -> bool {
+        matches!(
+            self.metrics.get_state(),
+            GC_STATE_TRACING | GC_STATE_WAITING_ON_MUTATORS
+        )
+    }
 
-        arena_size as f32 > (prev_arena_size as f32 * arena_size_ratio_trigger)
+    // Decides which collection should occur, with an explicitly requested
+    // major collection taking priority over the collection policy.
+    pub fn collection_trigger(&self) -> CollectionDecision {
+        if self.monitor_paused.load(Ordering::SeqCst) > 0 {
+            return CollectionDecision::None;
+        }
+
+        if self.major_requested.load(Ordering::SeqCst) {
+            return CollectionDecision::Major;
+        }
+
+        let stress = self.stress.pending();
+
+        if stress != CollectionDecision::None {
+            return stress;
+        }
+
+        self.get_arena_size();
+        let config = self.config.read().unwrap();
+        config.collection_policy.decide(&self.metrics.trigger_metrics(), &config)
+    }
+
+    pub fn stress_trigger(&self, config: &Config, point: StressPoint) {
+        if let Some(mode) = config.stress_mode.as_ref() {
+            self.stress.trigger(mode, point);
+        }
     }
 
-    pub fn get_trace_share_ratio(&self) -> f32 {
-        self.config.trace_share_ratio
+    // Returns the stress collection which has been triggered, unless the
+    // monitor is paused.
+    pub fn stress_pending(&self) -> CollectionDecision {
+        if self.monitor_paused.load(Ordering::SeqCst) > 0 {
+            return CollectionDecision::None;
+        }
+
+        self.stress.pending()
+    }
+
+    pub fn config(&self) -> Config {
+        self.config.read().unwrap().clone()
+    }
+
+    // Updates the config immediately if no collection is in progress, and
+    // otherwise once the ongoing collection has completed.
+    //
+    // The collection lock is not waited on, as it would deadlock if this is
+    // called from within a mutation while a collection is waiting on it.
+    pub fn update_config(&self, f: impl FnOnce(&mut Config)) -> Result<(), ConfigError> {
+        {
+            let mut pending = self.pending_config.lock().unwrap();
+            let mut config = pending.clone().unwrap_or_else(|| self.config());
+
+            f(&mut config);
+            config.validate()?;
+            *pending = Some(config);
+        }
+
+        if let Ok(_guard) = self.collection_lock.try_lock() {
+            self.apply_pending_config();
+        }
+
+        Ok(())
     }
 
-    pub fn config(&self) -> &Config {
-        &self.config
+    // Must be called while holding the collection lock.
+    fn apply_pending_config(&self) {
+        let Some(config) = self.pending_config.lock().unwrap().take() else {
+            return;
+        };
+        let prev_config = self.config();
+
+        if config.tracer_threads != prev_config.tracer_threads {
+            // dropping the old pool joins its threads, after which the
+            // stealers of their deques are no longer needed
+            *self.tracer_pool.lock().unwrap() = TracerPool::new(config.tracer_threads);
+            self.stealers.write().unwrap().clear();
+        }
+
+        if config.concurrent_sweep != prev_config.concurrent_sweep {
+            let mut sweeper = self.sweeper.lock().unwrap();
+
+            if let Some(sweeper) = sweeper.as_ref() {
+                sweeper.wait();
+            }
+
+            *sweeper = config
+                .concurrent_sweep
+                .then(|| Sweeper::new(self.heap.clone(), self.metrics.clone(), self.events.clone()));
+        }
+
+        *self.config.write().unwrap() = config;
     }
 
     pub fn metrics(&self) -> &Metrics {
@@ -362,9 +1021,18 @@ impl MultiThreadedCollector {
     }
 }
 
+impl Drop for MultiThreadedCollector {
+    fn drop(&mut self) {
+        for job in core::mem::take(self.finalizers.get_mut().unwrap()) {
+            // SAFETY: the arena is being dropped so every value is unreachable.
+            unsafe { job.finalize() }
+        }
+    }
+}
+
 // Monitor module for multi-threaded mode
 pub mod monitor {
-    use super::MultiThreadedCollector;
+    use super::{CollectionDecision, MultiThreadedCollector};
     use crate::trace::Trace;
     use alloc::sync::Arc;
     use core::ptr::NonNull;
@@ -384,7 +1052,7 @@ pub mod monitor {
         loop {
             monitor_sleep(&collector);
 
-            if collector.should_shutdown() {
+            if collector.should_stop_monitor() {
                 return;
             }
 
@@ -392,16 +1060,16 @@ pub mod monitor {
             // of the Arena, which outlives the monitor thread
             let root_ref = unsafe { root_ptr.0.as_ref() };
 
-            if collector.major_trigger() {
-                collector.major_collect(root_ref);
-            } else if collector.minor_trigger() {
-                collector.minor_collect(root_ref);
+            match collector.collection_trigger() {
+                CollectionDecision::Major => collector.major_collect(root_ref),
+                CollectionDecision::Minor => collector.minor_collect(root_ref),
+                CollectionDecision::None => {}
             }
         }
     }
 
     fn monitor_sleep(collector: &MultiThreadedCollector) {
-        let duration = std::time::Duration::from_millis(collector.config.monitor_wait_time);
+        let duration = std::time::Duration::from_millis(collector.config().monitor_wait_time);
         std::thread::sleep(duration);
     }
 }
@@ -0,0 +1,70 @@
+use alloc::alloc::Layout;
+use std::collections::{HashMap, HashSet};
+
+// nimix allocates small and medium values into blocks aligned to their size,
+// the end of which holds the block's line marks. Larger values are given an
+// allocation of their own, and are never moved.
+const BLOCK_SIZE: usize = 1024 * 16;
+const BLOCK_CAPACITY: usize = ((BLOCK_SIZE - 1) / 128) * 128;
+
+// Records the size of the live values in each block, found by the trace which
+// precedes a compaction.
+#[derive(Default)]
+pub struct Occupancy {
+    live: HashMap<usize, usize>,
+}
+
+impl Occupancy {
+    pub fn record(&mut self, ptr: *const u8, layout: Layout) {
+        if let Some(block) = block_of(ptr, layout) {
+            *self.live.entry(block).or_default() += layout.size();
+        }
+    }
+
+    pub fn merge(&mut self, other: Occupancy) {
+        for (block, live) in other.live {
+            *self.live.entry(block).or_default() += live;
+        }
+    }
+
+    // Only blocks which are less than half occupied are evacuated, as moving
+    // the values out of a dense block frees little space.
+    pub fn candidates(self) -> EvacuationCandidates {
+        let blocks = self
+            .live
+            .into_iter()
+            .filter(|&(_, live)| live * 2 < BLOCK_CAPACITY)
+            .map(|(block, _)| block)
+            .collect();
+
+        EvacuationCandidates { blocks }
+    }
+}
+
+// The blocks whose values a compacting trace moves, while the values in every
+// other block are left in place.
+pub struct EvacuationCandidates {
+    blocks: HashSet<usize>,
+}
+
+impl EvacuationCandidates {
+    pub fn contains(&self, ptr: *const u8, layout: Layout) -> bool {
+        block_of(ptr, layout).is_some_and(|block| self.blocks.contains(&block))
+    }
+
+    // Keeps a block, such as when moved values are allocated into its free
+    // space, after which the values remaining in it are left in place.
+    pub fn remove(&mut self, ptr: *const u8, layout: Layout) {
+        if let Some(block) = block_of(ptr, layout) {
+            self.blocks.remove(&block);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+    }
+}
+
+fn block_of(ptr: *const u8, layout: Layout) -> Option<usize> {
+    (layout.size() <= BLOCK_CAPACITY).then_some(ptr as usize & !(BLOCK_SIZE - 1))
+}
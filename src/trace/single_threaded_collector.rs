@@ -1,25 +1,60 @@
+use super::occupancy::Occupancy;
 use super::trace::Trace;
 use super::trace_job::TraceJob;
 use super::tracer::Tracer;
-use crate::config::Config;
+use super::weak_job::WeakJob;
+use crate::census::{CensusRecorder, HeapCensus};
+use crate::config::{Config, ConfigError};
 use crate::debug::gc_debug;
+use crate::event::{CollectionKind, EventListeners, GcEvent, GcEventListener};
+use crate::finalize::FinalizeJob;
+use crate::handle::RootSet;
 use crate::header::GcMark;
 use crate::heap::{Allocator, Heap};
+use crate::heap_dump::{DumpRecorder, HeapDump};
 use crate::metrics::{GC_STATE_SLEEPING, GC_STATE_SWEEPING, GC_STATE_TRACING};
 use crate::pointee::Thin;
+use crate::policy::CollectionDecision;
+use crate::stress::{StressPoint, StressState};
+use crate::verify::AllocationLog;
 use crate::Metrics;
+use alloc::alloc::Layout;
 use alloc::format;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::cell::RefCell;
 use core::ptr::NonNull;
-use core::sync::atomic::{AtomicU8, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use core::time::Duration;
+use std::time::Instant;
 
 pub struct SingleThreadedCollector {
     work_queue: RefCell<Vec<TraceJob>>,
+    finalizers: RefCell<Vec<FinalizeJob>>,
+    weak_refs: RefCell<Vec<WeakJob>>,
+    roots: Arc<RootSet>,
     heap: Heap,
     current_mark: AtomicU8,
-    pub config: Config,
+    major_requested: AtomicBool,
+    active_mutators: AtomicUsize,
+    monitor_on: AtomicBool,
+    monitor_paused: AtomicUsize,
+    config: RefCell<Config>,
     pub metrics: Metrics,
+    events: EventListeners,
+    // set while a major collection is taking a census of the heap
+    census: RefCell<Option<CensusRecorder>>,
+    // set while a major collection is recording the object graph
+    dump: RefCell<Option<DumpRecorder>>,
+    // set while a major collection is tracing ahead of a compaction
+    occupancy: RefCell<Option<Occupancy>>,
+    // the number of views, which hold references to values that must not be
+    // moved
+    active_views: AtomicUsize,
+    // the allocations made while heap verification is enabled
+    allocations: AllocationLog,
+    // the collection requested by the stress mode, if any
+    stress: StressState,
 }
 
 impl SingleThreadedCollector {
@@ -30,14 +65,40 @@ impl SingleThreadedCollector {
         Self {
             heap,
             work_queue: RefCell::new(Vec::new()),
+            finalizers: RefCell::new(Vec::new()),
+            weak_refs: RefCell::new(Vec::new()),
+            roots: Arc::new(RootSet::new()),
             current_mark: AtomicU8::new(GcMark::Red.into()),
+            major_requested: AtomicBool::new(false),
+            active_mutators: AtomicUsize::new(0),
+            monitor_on: AtomicBool::new(config.monitor_on),
+            monitor_paused: AtomicUsize::new(0),
             metrics,
-            config,
+            events: EventListeners::new(),
+            census: RefCell::new(None),
+            dump: RefCell::new(None),
+            occupancy: RefCell::new(None),
+            active_views: AtomicUsize::new(0),
+            allocations: AllocationLog::new(),
+            stress: StressState::new(),
+            config: RefCell::new(config),
         }
     }
 
     fn trace_and_sweep<T: Trace + ?Sized>(&self, root: &T) {
+        let allocated_bytes = self.metrics.get_allocated_bytes();
         self.trace(root);
+        self.clear_weak_refs();
+        self.finalize_unmarked();
+
+        // values referenced by a view must not be moved
+        if let Some(occupancy) = self.occupancy.take() {
+            if self.active_views.load(Ordering::SeqCst) == 0 {
+                self.compact(root, occupancy);
+            }
+        }
+
+        self.verify_heap(root);
 
         self.metrics
             .state
@@ -46,6 +107,11 @@ impl SingleThreadedCollector {
         unsafe {
             self.sweep();
         }
+        // values allocated while tracing are not included in the size of the
+        // arena before the collection, so they are still counted afterwards
+        self.metrics
+            .allocated_bytes
+            .fetch_sub(allocated_bytes, Ordering::Relaxed);
 
         self.print_debug_info();
     }
@@ -64,6 +130,7 @@ impl SingleThreadedCollector {
         let ptr: NonNull<Thin<T>> = NonNull::from(root).cast();
         let trace_job = TraceJob::new(ptr);
         self.work_queue.borrow_mut().push(trace_job);
+        self.work_queue.borrow_mut().append(&mut self.roots.jobs());
     }
 
     fn spawn_tracers(&self) {
@@ -73,18 +140,178 @@ impl SingleThreadedCollector {
     fn run_tracer(&self) {
         let mut tracer = self.new_tracer();
         let marked_objects = tracer.trace_loop() as u64;
+        self.weak_refs.borrow_mut().append(&mut tracer.take_weak());
         self.metrics
             .old_objects_count
             .fetch_add(marked_objects, Ordering::SeqCst);
+
+        if let Some(census) = tracer.take_census() {
+            if let Some(total) = self.census.borrow_mut().as_mut() {
+                total.merge(census);
+            }
+        }
+
+        if let Some(dump) = tracer.take_dump() {
+            if let Some(total) = self.dump.borrow_mut().as_mut() {
+                total.merge(dump);
+            }
+        }
+
+        if let Some(occupancy) = tracer.take_occupancy() {
+            if let Some(total) = self.occupancy.borrow_mut().as_mut() {
+                total.merge(occupancy);
+            }
+        }
     }
 
     fn new_tracer(&self) -> Tracer<'_> {
         let mark = self.get_current_mark();
-        Tracer::new(self, mark)
+        let mut tracer = Tracer::new(self, mark);
+
+        if self.census.borrow().is_some() {
+            tracer.enable_census();
+        }
+
+        if self.dump.borrow().is_some() {
+            tracer.enable_dump();
+        }
+
+        if self.occupancy.borrow().is_some() {
+            tracer.enable_occupancy();
+        }
+
+        tracer
+    }
+
+    fn clear_weak_refs(&self) {
+        let mark = self.get_current_mark();
+
+        for job in self.weak_refs.take() {
+            // SAFETY: the trace is complete and no sweep has occurred since
+            // the weak reference was recorded.
+            unsafe { job.clear_if_unmarked(mark) }
+        }
+    }
+
+    // Moves the values reachable from the root out of sparsely occupied
+    // blocks into newly allocated space, so that the sweep which follows
+    // frees the space they were moved out of. This requires a completed
+    // trace, and that no mutators or views exist.
+    fn compact<T: Trace + ?Sized>(&self, root: &T, occupancy: Occupancy) {
+        gc_debug("Compacting...");
+
+        let mark = self.rotate_mark();
+        let ptr: NonNull<Thin<T>> = NonNull::from(root).cast();
+        let mut roots = self.roots.jobs();
+        roots.push(TraceJob::new(ptr));
+
+        let allocator = Allocator::from(&self.heap);
+        let mut tracer = Tracer::new_compacting(self, mark, allocator, occupancy.candidates());
+        let forwarding = tracer.compact(roots);
+        let mut weak_refs = tracer.take_weak();
+        // the tracer's allocator must be dropped before sweeping
+        drop(tracer);
+
+        gc_debug(&format!("Moved {} objects", forwarding.len()));
+
+        self.roots.forward(&forwarding);
+
+        for job in self.finalizers.borrow_mut().iter_mut() {
+            job.forward(&forwarding);
+        }
+
+        for job in weak_refs.iter() {
+            // SAFETY: the weak references were reached by the compacting
+            // trace, and no sweep has occurred since.
+            unsafe { job.forward(&forwarding) }
+        }
+
+        self.weak_refs.borrow_mut().append(&mut weak_refs);
+
+        // Values marked by the preceding trace which are no longer reachable
+        // were not moved, so they must be treated as unreachable as well.
+        self.clear_weak_refs();
+        self.finalize_unmarked();
+    }
+
+    // Checks that every value reachable from the roots has been marked, then
+    // poisons the values which are about to be freed.
+    fn verify_heap<T: Trace + ?Sized>(&self, root: &T) {
+        if !self.config().verify_heap {
+            self.allocations.clear();
+            return;
+        }
+
+        let mut roots = self.roots.jobs();
+        roots.push(TraceJob::new(NonNull::from(root).cast::<Thin<T>>()));
+
+        let mark = self.get_current_mark();
+        Tracer::new_verifying(self, mark).verify(roots);
+
+        // SAFETY: the trace has completed and the sweep has not yet begun.
+        // Every logged allocation begins with a header.
+        unsafe { self.allocations.poison_unmarked(mark) };
+    }
+
+    pub fn log_allocation(&self, ptr: *const u8, layout: Layout) {
+        self.allocations.record(ptr, layout);
+    }
+
+    fn finalize_unmarked(&self) {
+        let mark = self.get_current_mark();
+        let (live, dead): (Vec<FinalizeJob>, Vec<FinalizeJob>) = self
+            .finalizers
+            .take()
+            .into_iter()
+            .partition(|job| job.is_marked(mark));
+
+        *self.finalizers.borrow_mut() = live;
+
+        gc_debug(&format!("Finalizing {} objects", dead.len()));
+
+        for job in dead {
+            // SAFETY: the trace is complete and the value was not marked,
+            // meaning it is unreachable, and it has not been swept yet.
+            unsafe { job.finalize() }
+        }
     }
 
     unsafe fn sweep(&self) {
+        let start_time = Instant::now();
+
         self.heap.sweep(self.get_current_mark());
+
+        self.events.emit(GcEvent::SweepFinished {
+            duration: start_time.elapsed(),
+            arena_size: self.get_arena_size(),
+        });
+    }
+
+    // Emits the start of a collection, returning the arena size and old
+    // object count needed to describe the collection once it has finished.
+    fn collection_started(&self, kind: CollectionKind) -> (u64, u64) {
+        let arena_size = self.get_arena_size();
+
+        self.stress.collection_started(kind);
+        self.events.emit(GcEvent::CollectionStarted { kind, arena_size });
+
+        (arena_size, self.metrics.get_old_objects_count())
+    }
+
+    fn collection_finished(&self, kind: CollectionKind, started: (u64, u64), duration: Duration) {
+        let (bytes_before, old_objects_before) = started;
+
+        self.events.emit(GcEvent::CollectionFinished {
+            kind,
+            duration,
+            bytes_before,
+            bytes_after: self.metrics.get_arena_size(),
+            objects_marked: self.metrics.get_old_objects_count().saturating_sub(old_objects_before),
+        });
+    }
+
+    pub fn add_event_listener(&self, listener: Arc<dyn GcEventListener>) {
+        self.events.add(listener);
     }
 
     fn print_debug_info(&self) {
@@ -108,45 +335,94 @@ impl SingleThreadedCollector {
         arena_size
     }
 
+    // Runs a major collection which records the type and size of every value
+    // it marks.
+    pub fn heap_census<T: Trace + ?Sized>(&self, root: &T) -> HeapCensus {
+        *self.census.borrow_mut() = Some(CensusRecorder::default());
+        self.major_collect(root);
+
+        self.census.take().unwrap_or_default().finish()
+    }
+
+    // Runs a major collection which records every value it marks and every
+    // pointer it traces. Compaction is skipped so that the recorded
+    // addresses remain valid.
+    pub fn heap_dump<T: Trace + ?Sized>(&self, root: &T) -> HeapDump {
+        *self.dump.borrow_mut() = Some(DumpRecorder::default());
+        self.major_collect(root);
+
+        self.dump.take().unwrap_or_default().finish()
+    }
+
     pub fn major_collect<T: Trace + ?Sized>(&self, root: &T) {
         gc_debug("Starting Major Collection");
 
+        self.major_requested.store(false, Ordering::SeqCst);
+
         self.metrics.old_objects_count.store(0, Ordering::Relaxed);
+        let started = self.collection_started(CollectionKind::Major);
+        let start_time = Instant::now();
         self.rotate_mark();
+
+        if self.config().compaction_on && self.dump.borrow().is_none() {
+            *self.occupancy.borrow_mut() = Some(Occupancy::default());
+        }
+
         self.trace_and_sweep(root);
+        // no mutators may run during a collection in single-threaded mode
+        let duration = start_time.elapsed();
 
-        self.metrics
-            .major_collections
-            .fetch_add(1, Ordering::Relaxed);
+        self.metrics.begin_update();
+        self.metrics.record_major_collection(duration, duration);
         self.metrics
             .prev_arena_size
             .store(self.get_arena_size(), Ordering::Relaxed);
         let old_objects = self.metrics.get_old_objects_count();
         self.metrics.max_old_objects.store(
-            (old_objects as f32 * self.config.monitor_max_old_growth_rate) as u64,
+            (old_objects as f32 * self.config().monitor_max_old_growth_rate) as u64,
             Ordering::Relaxed,
         );
+        self.metrics.end_update();
 
         self.metrics
             .state
             .store(GC_STATE_SLEEPING, Ordering::Relaxed);
+
+        self.collection_finished(CollectionKind::Major, started, duration);
+    }
+
+    // Runs a major collection unless a mutation is active, returning whether
+    // it ran.
+    pub fn try_major_collect<T: Trace + ?Sized>(&self, root: &T) -> bool {
+        if self.active_mutators.load(Ordering::SeqCst) > 0 {
+            return false;
+        }
+
+        self.major_collect(root);
+
+        true
     }
 
     pub fn minor_collect<T: Trace + ?Sized>(&self, root: &T) {
         gc_debug("Starting Minor Collection");
 
+        let started = self.collection_started(CollectionKind::Minor);
+        let start_time = Instant::now();
         self.trace_and_sweep(root);
+        let duration = start_time.elapsed();
 
-        self.metrics
-            .minor_collections
-            .fetch_add(1, Ordering::Relaxed);
+        self.metrics.begin_update();
+        self.metrics.record_minor_collection(duration, duration);
         self.metrics
             .prev_arena_size
             .store(self.get_arena_size(), Ordering::Relaxed);
+        self.metrics.end_update();
 
         self.metrics
             .state
             .store(GC_STATE_SLEEPING, Ordering::Relaxed);
+
+        self.collection_finished(CollectionKind::Minor, started, duration);
     }
 
     pub fn get_current_mark(&self) -> GcMark {
@@ -167,6 +443,31 @@ impl SingleThreadedCollector {
         Allocator::from(&self.heap)
     }
 
+    pub fn root_set(&self) -> &Arc<RootSet> {
+        &self.roots
+    }
+
+    pub fn request_major_collection(&self) {
+        self.major_requested.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_major_requested(&self) -> bool {
+        self.major_requested.load(Ordering::SeqCst)
+    }
+
+    // Runs `f` while preventing values from being moved.
+    pub fn view<R>(&self, f: impl FnOnce() -> R) -> R {
+        self.active_views.fetch_add(1, Ordering::SeqCst);
+        let result = f();
+        self.active_views.fetch_sub(1, Ordering::SeqCst);
+
+        result
+    }
+
+    pub fn register_finalizer(&self, job: FinalizeJob) {
+        self.finalizers.borrow_mut().push(job);
+    }
+
     pub fn send_work(&self, mut work: Vec<TraceJob>) {
         self.work_queue.borrow_mut().append(&mut work);
     }
@@ -184,50 +485,123 @@ impl SingleThreadedCollector {
         !self.work_queue.borrow().is_empty()
     }
 
+    pub fn pace_mutator(&self, _config: &Config) {
+        // No-op in single-threaded mode
+    }
+
+    pub fn record_marks(&self, _marks: usize) {
+        // No-op in single-threaded mode
+    }
+
     pub fn yield_flag(&self) -> bool {
-        self.minor_trigger() || self.major_trigger()
+        self.collection_trigger() != CollectionDecision::None
     }
 
+    // Mutators are only counted so that a collection is not started from
+    // within a nested mutation.
     pub fn increment_mutators(&self) {
-        // No-op in single-threaded mode
+        self.active_mutators.fetch_add(1, Ordering::SeqCst);
     }
 
     pub fn decrement_mutators(&self) {
-        // No-op in single-threaded mode
+        self.active_mutators.fetch_sub(1, Ordering::SeqCst);
     }
 
-    pub fn major_trigger(&self) -> bool {
-        let current_old_objects_count = self.metrics.old_objects_count.load(Ordering::Relaxed);
-        let max_old_objects_count = self.metrics.max_old_objects.load(Ordering::Relaxed);
+    pub fn park_mutator<T: Trace>(&self, _roots: &T) -> bool {
+        // No collection can occur during a mutation in single-threaded mode
+        false
+    }
 
-        let result = current_old_objects_count > max_old_objects_count;
-        if result {
-            self.print_debug_info();
-        };
-        result
+    // There is no monitor thread in single-threaded mode, instead the monitor
+    // controls whether collections are triggered at the end of a mutation.
+    pub fn start_monitor(&self) {
+        self.monitor_on.store(true, Ordering::SeqCst);
     }
 
-    pub fn minor_trigger(&self) -> bool {
-        let arena_size = self.get_arena_size();
-        let prev_arena_size = self.metrics.prev_arena_size.load(Ordering::Relaxed);
-        let arena_size_ratio_trigger = self.config.monitor_arena_size_ratio_trigger;
+    pub fn stop_monitor(&self) {
+        self.monitor_on.store(false, Ordering::SeqCst);
+    }
 
-        arena_size as f32 > (prev_arena_size as f32 * arena_size_ratio_trigger)
+    pub fn pause_monitor(&self) {
+        self.monitor_paused.fetch_add(1, Ordering::SeqCst);
     }
 
-    pub fn check_yield(&self) {
+    pub fn resume_monitor(&self) {
+        let _ = self
+            .monitor_paused
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |paused| {
+                paused.checked_sub(1)
+            });
+    }
+
+    // Decides which collection should occur, with an explicitly requested
+    // major collection taking priority over the collection policy.
+    // Requested and stress collections are performed even while the monitor
+    // is off.
+    pub fn collection_trigger(&self) -> CollectionDecision {
+        if self.monitor_paused.load(Ordering::SeqCst) > 0 {
+            return CollectionDecision::None;
+        }
+
+        if self.major_requested.load(Ordering::SeqCst) {
+            return CollectionDecision::Major;
+        }
+
+        let stress = self.stress.pending();
+
+        if stress != CollectionDecision::None {
+            return stress;
+        }
+
+        if !self.monitor_on.load(Ordering::SeqCst) {
+            return CollectionDecision::None;
+        }
+
+        self.get_arena_size();
+        let config = self.config.borrow();
+        let decision = config.collection_policy.decide(&self.metrics.trigger_metrics(), &config);
+        if decision == CollectionDecision::Major {
+            self.print_debug_info();
+        }
+        decision
+    }
+
+    pub fn stress_trigger(&self, config: &Config, point: StressPoint) {
+        if let Some(mode) = config.stress_mode.as_ref() {
+            self.stress.trigger(mode, point);
+        }
+    }
+
+    pub fn check_yield(&self, _config: &Config) {
         // No-op in single-threaded mode
     }
 
-    pub fn get_trace_share_ratio(&self) -> f32 {
-        self.config.trace_share_ratio
+    pub fn config(&self) -> Config {
+        self.config.borrow().clone()
     }
 
-    pub fn config(&self) -> &Config {
-        &self.config
+    // No collection can be in progress while the config is being updated in
+    // single-threaded mode, so the update is applied immediately.
+    pub fn update_config(&self, f: impl FnOnce(&mut Config)) -> Result<(), ConfigError> {
+        let mut config = self.config();
+
+        f(&mut config);
+        config.validate()?;
+        *self.config.borrow_mut() = config;
+
+        Ok(())
     }
 
     pub fn metrics(&self) -> &Metrics {
         &self.metrics
     }
 }
+
+impl Drop for SingleThreadedCollector {
+    fn drop(&mut self) {
+        for job in self.finalizers.take() {
+            // SAFETY: the arena is being dropped so every value is unreachable.
+            unsafe { job.finalize() }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use crate::event::{EventListeners, GcEvent};
+use crate::header::GcMark;
+use crate::heap::Heap;
+use crate::Metrics;
+use alloc::sync::Arc;
+use core::sync::atomic::Ordering;
+use std::sync::{Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+// A thread which sweeps the heap in the background, so that mutators may
+// resume as soon as a trace has completed. Only one sweep may be pending at a
+// time, and it must be waited on before the heap is traced again, as marking
+// would otherwise change the marks the sweep uses to find free space.
+//
+// Values allocated after the trace are unmarked, so the sweep must lock the
+// heap's blocks before mutators resume. Mutators which allocate during the
+// sweep then wait for it to finish, while mutators which do not allocate are
+// unaffected by it.
+pub struct Sweeper {
+    shared: Arc<SweeperShared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+struct SweeperShared {
+    heap: Arc<Heap>,
+    metrics: Arc<Metrics>,
+    events: Arc<EventListeners>,
+    state: Mutex<SweeperState>,
+    start: Condvar,
+    done: Condvar,
+}
+
+struct SweeperState {
+    // the mark of the live values in the pending sweep
+    pending: Option<GcMark>,
+    // set once the pending sweep has locked the heap's blocks
+    locked: bool,
+    shutdown: bool,
+}
+
+impl Sweeper {
+    pub fn new(heap: Arc<Heap>, metrics: Arc<Metrics>, events: Arc<EventListeners>) -> Self {
+        let shared = Arc::new(SweeperShared {
+            heap,
+            metrics,
+            events,
+            state: Mutex::new(SweeperState {
+                pending: None,
+                locked: false,
+                shutdown: false,
+            }),
+            start: Condvar::new(),
+            done: Condvar::new(),
+        });
+
+        let thread_shared = shared.clone();
+        let thread = std::thread::spawn(move || thread_shared.run_thread());
+
+        Self {
+            shared,
+            thread: Some(thread),
+        }
+    }
+
+    // Starts sweeping every value not marked with `live_mark`, and returns
+    // once the sweep has locked the heap's blocks, without waiting for it to
+    // finish.
+    //
+    // SAFETY: same as `Heap::sweep`, in addition, the heap must not be traced
+    // until `wait` has been called.
+    pub unsafe fn sweep(&self, live_mark: GcMark) {
+        let mut state = self.shared.state.lock().unwrap();
+
+        while state.pending.is_some() {
+            state = self.shared.done.wait(state).unwrap();
+        }
+
+        state.pending = Some(live_mark);
+        self.shared.start.notify_all();
+
+        while state.pending.is_some() && !state.locked {
+            state = self.shared.done.wait(state).unwrap();
+        }
+    }
+
+    pub fn is_sweeping(&self) -> bool {
+        self.shared.state.lock().unwrap().pending.is_some()
+    }
+
+    // Blocks until the pending sweep, if there is one, has finished.
+    pub fn wait(&self) {
+        let mut state = self.shared.state.lock().unwrap();
+
+        while state.pending.is_some() {
+            state = self.shared.done.wait(state).unwrap();
+        }
+    }
+}
+
+impl SweeperShared {
+    fn run_thread(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        loop {
+            if let Some(live_mark) = state.pending {
+                let start_time = Instant::now();
+                let guard = self.heap.lock_blocks();
+
+                state.locked = true;
+                self.done.notify_all();
+                drop(state);
+
+                unsafe { self.heap.sweep_locked(live_mark, &guard) };
+                drop(guard);
+
+                // The collection leaves the arena size to be recorded here,
+                // as it cannot be measured until the sweep has finished.
+                let arena_size = self.heap.get_size();
+                self.metrics.begin_update();
+                self.metrics.arena_size.store(arena_size, Ordering::Relaxed);
+                self.metrics
+                    .prev_arena_size
+                    .store(arena_size, Ordering::Relaxed);
+                self.metrics.end_update();
+
+                self.events.emit(GcEvent::SweepFinished {
+                    duration: start_time.elapsed(),
+                    arena_size,
+                });
+
+                state = self.state.lock().unwrap();
+                state.pending = None;
+                state.locked = false;
+                self.done.notify_all();
+                continue;
+            }
+
+            if state.shutdown {
+                return;
+            }
+
+            state = self.start.wait(state).unwrap();
+        }
+    }
+}
+
+impl Drop for Sweeper {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().shutdown = true;
+        self.shared.start.notify_all();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use crate::config::Config;
+use crate::metrics::update_avg_u64;
+use crate::Metrics;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+use std::sync::Mutex;
+use std::time::Instant;
+
+// Rate limits mutators during a trace so that the tracers outpace them.
+//
+// Time is divided into slices of `Config::collector_timeslice_size`. At the
+// start of each slice the amount of time mutators must yield for is
+// calculated from how much of the collector's headroom has been allocated
+// since the trace began, and how much of the trace remains, and every mutator
+// that calls `gc_yield` within that window is blocked until it has passed.
+pub struct Timeslicer {
+    slice: Mutex<Option<Slice>>,
+    // the allocated bytes when the trace began
+    allocated_at_start: AtomicU64,
+    // the number of values marked by the trace so far
+    marked: AtomicU64,
+    // the number of values marked by the previous trace, which the progress
+    // of the current trace is measured against
+    expected_marks: AtomicU64,
+}
+
+struct Slice {
+    start: Instant,
+    yield_until: Instant,
+}
+
+impl Timeslicer {
+    pub fn new() -> Self {
+        Self {
+            slice: Mutex::new(None),
+            allocated_at_start: AtomicU64::new(0),
+            marked: AtomicU64::new(0),
+            expected_marks: AtomicU64::new(0),
+        }
+    }
+
+    // Discards the current slice, so that the first slice of a trace begins
+    // once a mutator yields, rather than depending on the previous trace, and
+    // begins measuring the allocations and progress of the trace.
+    pub fn start_trace(&self, metrics: &Metrics) {
+        *self.slice.lock().unwrap() = None;
+        self.allocated_at_start
+            .store(metrics.get_allocated_bytes(), Ordering::Relaxed);
+        self.marked.store(0, Ordering::Relaxed);
+    }
+
+    pub fn finish_trace(&self) {
+        self.expected_marks
+            .store(self.marked.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+
+    pub fn record_marks(&self, marks: usize) {
+        self.marked.fetch_add(marks as u64, Ordering::Relaxed);
+    }
+
+    // Blocks the calling mutator for the remainder of the current slice's
+    // yield window, if it is in one.
+    pub fn pace(&self, config: &Config, metrics: &Metrics) {
+        let now = Instant::now();
+        let yield_until = {
+            let mut slice = self.slice.lock().unwrap();
+            let slice_size = millis(config.collector_timeslice_size);
+
+            match slice.as_ref() {
+                Some(slice) if now.duration_since(slice.start) < slice_size => slice.yield_until,
+                _ => {
+                    let yield_until = now + self.yield_time(config, metrics);
+
+                    *slice = Some(Slice {
+                        start: now,
+                        yield_until,
+                    });
+
+                    yield_until
+                }
+            }
+        };
+
+        if now >= yield_until {
+            return;
+        }
+
+        let yield_time = yield_until - now;
+
+        std::thread::sleep(yield_time);
+        Self::record_yield(metrics, yield_time);
+    }
+
+    // The portion of a slice mutators must yield for, which grows with the
+    // fraction of the headroom that has been allocated since the trace began,
+    // until mutators are yielding for the entire slice, and shrinks as the
+    // trace nears completion. Until a trace has completed, the progress of
+    // the next one cannot be measured and is taken to be none. Yields shorter
+    // than the slice min are not requested.
+    fn yield_time(&self, config: &Config, metrics: &Metrics) -> Duration {
+        let headroom =
+            config.collector_max_headroom_ratio * metrics.get_prev_arena_size() as f64;
+
+        if headroom <= 0.0 {
+            return Duration::ZERO;
+        }
+
+        let allocated = metrics
+            .get_allocated_bytes()
+            .saturating_sub(self.allocated_at_start.load(Ordering::Relaxed));
+        let expected_marks = self.expected_marks.load(Ordering::Relaxed);
+        let progress = if expected_marks == 0 {
+            0.0
+        } else {
+            (self.marked.load(Ordering::Relaxed) as f64 / expected_marks as f64).min(1.0)
+        };
+
+        let pressure = (allocated as f64 / headroom).min(1.0) * (1.0 - progress);
+        let yield_time = pressure * config.collector_timeslice_size;
+
+        if yield_time < config.collector_slice_min {
+            return Duration::ZERO;
+        }
+
+        millis(yield_time)
+    }
+
+    fn record_yield(metrics: &Metrics, yield_time: Duration) {
+        let yield_time = yield_time.as_nanos() as u64;
+        let yields = metrics.timeslice_yields.fetch_add(1, Ordering::Relaxed);
+
+        metrics.max_yield_time.fetch_max(yield_time, Ordering::Relaxed);
+        update_avg_u64(&metrics.avg_yield_time, yield_time, yields);
+    }
+}
+
+fn millis(ms: f64) -> Duration {
+    Duration::from_secs_f64(ms.max(0.0) / 1000.0)
+}
@@ -1,5 +1,5 @@
 use super::tracer::Tracer;
-use crate::gc::{Gc, GcOpt};
+use crate::gc::{Gc, GcOpt, GcWeak};
 use crate::pointee::{GcPointee, Thin};
 use crate::tagged::{Tag, Tagged};
 use core::cell::*;
@@ -66,6 +66,8 @@ impl<T: Drop> __MustNotDrop for T {}
 /// Types implementing [`Trace`] may not impl Drop, as this GC does not
 /// support dropping freed values. This is prevented via a conflicting Drop
 /// impl that will occur when attempting to impl Trace on a type that impls Drop.
+/// Types which need to release resources when freed can instead impl
+/// [`crate::Finalize`] and be allocated via [`crate::Mutator::alloc_finalized`].
 ///
 /// ## Safety:
 /// Can safely be implemented using `#[derive(Trace)]`. Implmenting
@@ -100,6 +102,10 @@ unsafe impl<'gc, T: Trace + ?Sized> Trace for Gc<'gc, T> {
     const IS_LEAF: bool = false;
 
     fn trace(&self, tracer: &mut Tracer) {
+        if tracer.is_compacting() {
+            return self.evacuate(tracer);
+        }
+
         tracer.mark_and_trace(self.clone());
     }
 }
@@ -108,17 +114,30 @@ unsafe impl<'gc, T: Trace + ?Sized> Trace for GcOpt<'gc, T> {
     const IS_LEAF: bool = false;
 
     fn trace(&self, tracer: &mut Tracer) {
+        if tracer.is_compacting() {
+            return self.evacuate(tracer);
+        }
+
         if let Some(gc_mut) = self.as_option() {
             gc_mut.trace(tracer)
         }
     }
 }
 
+unsafe impl<'gc, T: Trace + ?Sized> Trace for GcWeak<'gc, T> {
+    const IS_LEAF: bool = false;
+
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.record_weak(self);
+    }
+}
+
 unsafe impl<'gc, T: Tag> Trace for Tagged<'gc, T> {
     const IS_LEAF: bool = false;
 
     fn trace(&self, tracer: &mut crate::Tracer) {
         T::trace_tagged(self, tracer);
+        tracer.forward_tagged(self);
     }
 }
 
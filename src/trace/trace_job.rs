@@ -1,5 +1,7 @@
+use super::forwarding::Forwarding;
 use super::trace::Trace;
 use super::tracer::Tracer;
+use crate::gc::Gc;
 use crate::pointee::Thin;
 use core::ptr::NonNull;
 use std::hash::Hash;
@@ -35,7 +37,34 @@ impl TraceJob {
         }
     }
 
+    // Unlike a regular job which traces the contents of a value, a root job
+    // marks the GC value itself before tracing it.
+    pub fn new_root<T: Trace + ?Sized>(ptr: NonNull<Thin<T>>) -> Self {
+        Self {
+            ptr: ptr.cast(),
+            dyn_trace: trace_root::<T>,
+        }
+    }
+
+    pub fn as_ptr(&self) -> NonNull<Thin<()>> {
+        self.ptr
+    }
+
+    pub fn forward(&mut self, forwarding: &Forwarding) {
+        if let Some(new) = forwarding.get(self.ptr.as_ptr() as usize) {
+            self.ptr = new;
+        }
+    }
+
     pub fn trace(&self, tracer: &mut Tracer) {
+        tracer.set_dump_parent(Some(self.ptr));
         (self.dyn_trace)(self.ptr, tracer);
     }
 }
+
+fn trace_root<T: Trace + ?Sized>(ptr: NonNull<Thin<()>>, tracer: &mut Tracer) {
+    let gc: Gc<'_, T> = unsafe { Gc::from_thin(ptr.cast()) };
+
+    tracer.set_dump_parent(None);
+    gc.trace(tracer);
+}
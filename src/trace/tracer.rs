@@ -1,32 +1,156 @@
 use super::collector::Collector;
+use super::ephemeron_job::EphemeronJob;
+use super::forwarding::Forwarding;
+use super::occupancy::{EvacuationCandidates, Occupancy};
 use super::trace::Trace;
 use super::trace_job::TraceJob;
+use super::weak_job::WeakJob;
+use crate::census::CensusRecorder;
+use crate::config::Config;
 use crate::debug::{gc_debug, gc_trace};
-use crate::gc::Gc;
+use crate::ephemeron::Ephemeron;
+use crate::gc::{Gc, GcWeak};
+use crate::heap_dump::{DumpRecorder, HeapNode};
 use crate::header::{GcHeader, GcMark};
-use crate::heap::mark;
+use crate::heap::{mark, Allocator};
+use crate::pointee::Thin;
+use crate::tagged::{Tag, Tagged};
+use alloc::collections::BTreeSet;
 use alloc::format;
 use alloc::vec;
 use alloc::vec::Vec;
+use core::ptr::{copy_nonoverlapping, NonNull};
+use core::sync::atomic::{AtomicPtr, Ordering};
 
 /// Internal type used by the GC to perform tracing.
 pub struct Tracer<'a> {
     collector: &'a Collector,
+    // read once when the tracer is created, rather than for every chunk of
+    // work
+    config: Config,
     mark: GcMark,
     pub mark_count: usize,
     work: Vec<TraceJob>,
+    weak: Vec<WeakJob>,
+    ephemerons: Vec<EphemeronJob>,
+    evacuation: Option<Evacuation>,
+    census: Option<CensusRecorder>,
+    dump: Option<DumpRecorder>,
+    // set while tracing ahead of a compaction
+    occupancy: Option<Occupancy>,
+    // set while verifying the heap, holding every value visited so far
+    verified: Option<BTreeSet<usize>>,
+}
+
+// The state of a compacting trace, in which every value reached within an
+// evacuation candidate is moved into space obtained from a fresh allocator.
+struct Evacuation {
+    allocator: Allocator,
+    forwarding: Forwarding,
+    candidates: EvacuationCandidates,
+    // whether moved values must be recorded for heap verification
+    log_allocations: bool,
 }
 
 impl<'a> Tracer<'a> {
     pub(crate) fn new(collector: &'a Collector, mark: GcMark) -> Self {
         Self {
             collector,
+            config: collector.config(),
             mark,
             mark_count: 0,
             work: vec![],
+            weak: vec![],
+            ephemerons: vec![],
+            evacuation: None,
+            census: None,
+            dump: None,
+            occupancy: None,
+            verified: None,
+        }
+    }
+
+    // Records the type and size of every value this tracer marks.
+    pub(crate) fn enable_census(&mut self) {
+        self.census = Some(CensusRecorder::default());
+    }
+
+    pub(crate) fn take_census(&mut self) -> Option<CensusRecorder> {
+        self.census.take()
+    }
+
+    // Records every value this tracer marks, along with each pointer it
+    // traces, regardless of whether the pointee was already marked.
+    pub(crate) fn enable_dump(&mut self) {
+        self.dump = Some(DumpRecorder::default());
+    }
+
+    pub(crate) fn take_dump(&mut self) -> Option<DumpRecorder> {
+        self.dump.take()
+    }
+
+    // Sets the value whose pointers are about to be traced, or `None` if they
+    // are held by a root.
+    pub(crate) fn set_dump_parent(&mut self, parent: Option<NonNull<Thin<()>>>) {
+        if let Some(dump) = self.dump.as_mut() {
+            dump.set_parent(parent.map(|ptr| ptr.as_ptr() as usize));
+        }
+    }
+
+    // Records how much of each block is occupied by the values this tracer
+    // marks.
+    pub(crate) fn enable_occupancy(&mut self) {
+        self.occupancy = Some(Occupancy::default());
+    }
+
+    pub(crate) fn take_occupancy(&mut self) -> Option<Occupancy> {
+        self.occupancy.take()
+    }
+
+    pub(crate) fn new_compacting(
+        collector: &'a Collector,
+        mark: GcMark,
+        allocator: Allocator,
+        candidates: EvacuationCandidates,
+    ) -> Self {
+        let mut tracer = Self::new(collector, mark);
+
+        tracer.evacuation = Some(Evacuation {
+            allocator,
+            forwarding: Forwarding::new(),
+            candidates,
+            log_allocations: tracer.config.verify_heap,
+        });
+
+        tracer
+    }
+
+    // A verifying tracer does not mark anything. Instead it checks that every
+    // value reachable from the roots it is given has already been marked.
+    pub(crate) fn new_verifying(collector: &'a Collector, mark: GcMark) -> Self {
+        let mut tracer = Self::new(collector, mark);
+
+        tracer.verified = Some(BTreeSet::new());
+        tracer
+    }
+
+    pub(crate) fn is_verifying(&self) -> bool {
+        self.verified.is_some()
+    }
+
+    // Panics if any value reachable from the given jobs is not marked.
+    pub(crate) fn verify(&mut self, roots: Vec<TraceJob>) {
+        self.work = roots;
+
+        while let Some(job) = self.work.pop() {
+            job.trace(self);
         }
     }
 
+    pub(crate) fn is_compacting(&self) -> bool {
+        self.evacuation.is_some()
+    }
+
     pub(crate) fn mark<T: Trace + ?Sized>(&mut self, gc: Gc<'_, T>) -> bool {
         gc_trace(&format!(
             "marking\t{}\tptr\t{:#x}\theader\t{:#x}",
@@ -38,8 +162,26 @@ impl<'a> Tracer<'a> {
         let header = gc.get_header();
         let alloc_ptr = gc.get_header_ptr();
         let alloc_layout = gc.get_layout();
+        let prev_mark = header.get_mark();
+
+        if let Some(verified) = self.verified.as_mut() {
+            let address = gc.as_thin().as_ptr() as usize;
 
-        if header.get_mark() == self.mark {
+            assert!(
+                prev_mark == self.mark,
+                "heap verification failed: reachable {} at {:#x} is not marked",
+                core::any::type_name::<T>(),
+                address
+            );
+
+            return verified.insert(address);
+        }
+
+        if let Some(dump) = self.dump.as_mut() {
+            dump.record_edge(gc.as_thin().as_ptr() as usize);
+        }
+
+        if prev_mark == self.mark {
             return false;
         }
 
@@ -47,8 +189,25 @@ impl<'a> Tracer<'a> {
 
         self.increment_mark_count();
 
+        if let Some(census) = self.census.as_mut() {
+            census.record(core::any::type_name::<T>(), alloc_layout.size());
+        }
+
+        if let Some(dump) = self.dump.as_mut() {
+            dump.record_node(HeapNode {
+                address: gc.as_thin().as_ptr() as usize,
+                type_name: core::any::type_name::<T>(),
+                size: alloc_layout.size(),
+                mark: prev_mark.into(),
+            });
+        }
+
         unsafe { mark(alloc_ptr as *mut u8, alloc_layout, self.mark) };
 
+        if let Some(occupancy) = self.occupancy.as_mut() {
+            occupancy.record(alloc_ptr as *const u8, alloc_layout);
+        }
+
         return true;
     }
 
@@ -60,16 +219,200 @@ impl<'a> Tracer<'a> {
         self.work.push(TraceJob::new(gc.as_thin()));
     }
 
+    // During a compacting trace, moves the value `ptr` points at if it is in
+    // an evacuation candidate (unless it has already been moved), and updates
+    // `ptr` to point at the new location, before marking and tracing the
+    // value wherever it now is.
+    pub(crate) fn evacuate<T: Trace + ?Sized>(&mut self, ptr: &AtomicPtr<Thin<T>>) {
+        if let Some(value) = self.move_value(ptr) {
+            self.mark_and_trace(unsafe { Gc::from_thin(value) });
+        }
+    }
+
+    // Same as `evacuate`, except the value is only marked and not traced.
+    pub(crate) fn evacuate_untraced<T: Trace + ?Sized>(&mut self, ptr: &AtomicPtr<Thin<T>>) {
+        if let Some(value) = self.move_value(ptr) {
+            self.mark(unsafe { Gc::from_thin(value) });
+        }
+    }
+
+    // Returns the value's location, after moving it, if this call is the
+    // first to reach it.
+    fn move_value<T: Trace + ?Sized>(&mut self, ptr: &AtomicPtr<Thin<T>>) -> Option<NonNull<Thin<T>>> {
+        let evacuation = self.evacuation.as_mut()?;
+        let old = NonNull::new(ptr.load(Ordering::SeqCst))?;
+        let gc: Gc<'_, T> = unsafe { Gc::from_thin(old) };
+
+        // values which have already been reached carry the new mark, whether
+        // they were moved or left in place
+        if gc.get_header().get_mark() == self.mark {
+            return None;
+        }
+
+        if let Some(new) = evacuation.forwarding.get(old.as_ptr() as usize) {
+            ptr.store(new.cast().as_ptr(), Ordering::SeqCst);
+            return None;
+        }
+
+        let layout = gc.get_layout();
+        let header_ptr = gc.get_header_ptr() as *const u8;
+        let value_offset = old.as_ptr() as usize - header_ptr as usize;
+
+        if !evacuation.candidates.contains(header_ptr, layout) {
+            return Some(old);
+        }
+
+        let Ok(new_ptr) = evacuation.allocator.try_alloc(layout) else {
+            // the heap is exhausted, so compaction is abandoned and the values
+            // which have not been moved yet are left in place
+            gc_debug("Compaction aborted, failed to allocate");
+            evacuation.candidates.clear();
+
+            return Some(old);
+        };
+
+        // a candidate whose free space is being filled is no longer evacuated
+        evacuation.candidates.remove(new_ptr, layout);
+
+        // SAFETY: the new allocation has the same layout as the old one, and
+        // the value is not accessed through the old location again, other
+        // than by finalizers and weak references which are forwarded or
+        // cleared before the sweep.
+        let new = unsafe {
+            let new_ptr = new_ptr as *mut u8;
+
+            copy_nonoverlapping(header_ptr, new_ptr, layout.size());
+
+            if evacuation.log_allocations {
+                self.collector.log_allocation(new_ptr, layout);
+            }
+
+            NonNull::new_unchecked(new_ptr.add(value_offset)).cast::<Thin<T>>()
+        };
+
+        evacuation.forwarding.insert(old.cast(), new.cast());
+        ptr.store(new.as_ptr(), Ordering::SeqCst);
+
+        Some(new)
+    }
+
+    // A tagged pointer's value is moved when traced through a temporary Gc,
+    // after which the tagged pointer itself must be updated.
+    pub(crate) fn forward_tagged<T: Tag>(&self, tagged: &Tagged<'_, T>) {
+        let Some(evacuation) = self.evacuation.as_ref() else {
+            return;
+        };
+
+        if !tagged.is_ptr() {
+            return;
+        }
+
+        if let Some(new) = evacuation.forwarding.get(tagged.get_stripped_raw()) {
+            let raw = Tagged::<T>::apply_tag(new.as_ptr() as usize, tagged.get_tag());
+
+            unsafe { tagged.set(raw) };
+        }
+    }
+
+    // Traces everything reachable from the given jobs while moving the values
+    // in evacuation candidates, returning where each value was moved to.
+    pub(crate) fn compact(&mut self, roots: Vec<TraceJob>) -> Forwarding {
+        self.work = roots;
+
+        while let Some(job) = self.work.pop() {
+            job.trace(self);
+        }
+
+        let evacuation = self.evacuation.take().expect("tracer is not compacting");
+
+        evacuation.forwarding
+    }
+
+    // Weak references are not traced through, instead they are recorded so that
+    // they can be cleared if their referent is not marked by the end of the trace.
+    pub(crate) fn record_weak<T: Trace + ?Sized>(&mut self, weak: &GcWeak<'_, T>) {
+        if weak.is_some() {
+            self.weak.push(WeakJob::new(weak));
+        }
+    }
+
+    pub(crate) fn take_weak(&mut self) -> Vec<WeakJob> {
+        core::mem::take(&mut self.weak)
+    }
+
+    // The value of an ephemeron is only traced once its key has been marked.
+    // If the key is not yet marked, the ephemeron is deferred until this tracer
+    // runs out of work, at which point it is checked again.
+    pub(crate) fn trace_ephemeron<K: Trace + ?Sized, V: Trace + ?Sized>(
+        &mut self,
+        ephemeron: &Ephemeron<'_, K, V>,
+    ) {
+        if ephemeron.is_none() {
+            return;
+        }
+
+        self.weak.push(WeakJob::new(ephemeron));
+
+        // Any ephemeron reached by a compacting trace survived the preceding
+        // trace, so its value is live and must be moved.
+        if self.is_compacting() {
+            ephemeron.trace_value(self);
+            return;
+        }
+
+        if !ephemeron.trace_value_if_key_marked(self) {
+            let owner = self.dump.as_ref().and_then(DumpRecorder::parent);
+
+            self.ephemerons.push(EphemeronJob::new(ephemeron, owner));
+        }
+    }
+
+    #[cfg(feature = "multi_threaded")]
+    pub(crate) fn take_ephemerons(&mut self) -> Vec<EphemeronJob> {
+        core::mem::take(&mut self.ephemerons)
+    }
+
+    #[cfg(feature = "multi_threaded")]
+    pub(crate) fn add_ephemerons(&mut self, mut ephemerons: Vec<EphemeronJob>) {
+        self.ephemerons.append(&mut ephemerons);
+    }
+
+    // Returns true if any deferred ephemeron had its key marked.
+    fn resolve_ephemerons(&mut self) -> bool {
+        let pending = core::mem::take(&mut self.ephemerons);
+        let count = pending.len();
+
+        for job in pending {
+            // a heap dump attributes the pointer to the ephemeron's value to
+            // the value holding the ephemeron
+            if let Some(dump) = self.dump.as_mut() {
+                dump.set_parent(job.owner());
+            }
+
+            if !job.resolve(self) {
+                self.ephemerons.push(job);
+            }
+        }
+
+        self.ephemerons.len() < count
+    }
+
     pub(crate) fn trace_loop(&mut self) -> usize {
         loop {
             if self.work.is_empty() {
                 match self.collector.recv_work() {
                     Some(work) => self.work = work,
-                    None => break,
+                    None => {
+                        if self.resolve_ephemerons() {
+                            continue;
+                        }
+
+                        break;
+                    }
                 }
             }
 
-            self.collector.check_yield();
+            self.collector.check_yield(&self.config);
 
             self.do_work();
             self.share_work();
@@ -81,21 +424,24 @@ impl<'a> Tracer<'a> {
     }
 
     fn do_work(&mut self) {
-        for _ in 0..self.collector.config().trace_chunk_size {
+        let mark_count = self.mark_count;
+
+        for _ in 0..self.config.trace_chunk_size {
             match self.work.pop() {
                 Some(job) => job.trace(self),
                 None => break,
             }
         }
+
+        self.collector.record_marks(self.mark_count - mark_count);
     }
 
     fn share_work(&mut self) {
-        if self.collector.config().trace_share_min >= self.work.len() || self.collector.has_work() {
+        if self.config.trace_share_min >= self.work.len() || self.collector.has_work() {
             return;
         }
 
-        let split_at =
-            (self.work.len() as f32 * self.collector.get_trace_share_ratio()) as usize;
+        let split_at = (self.work.len() as f32 * self.config.trace_share_ratio) as usize;
         let share_work = self.work.split_off(split_at);
 
         if !share_work.is_empty() {
@@ -0,0 +1,136 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Condvar, Mutex};
+use std::thread::JoinHandle;
+
+// A task borrowed from the thread calling `TracerPool::run`. The borrow is
+// extended to 'static, which is sound only b/c `run` blocks until every thread
+// has finished running the task.
+type Task = &'static (dyn Fn() + Sync);
+
+// A fixed set of threads which are kept alive for the lifetime of the
+// collector, so that each trace does not need to spawn its own tracer threads.
+pub struct TracerPool {
+    shared: Arc<PoolShared>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+struct PoolShared {
+    state: Mutex<PoolState>,
+    start: Condvar,
+    done: Condvar,
+}
+
+struct PoolState {
+    task: Option<Task>,
+    // incremented each time a task is started, so that threads can tell when
+    // a new task is available
+    epoch: usize,
+    running: usize,
+    panicked: bool,
+    shutdown: bool,
+}
+
+impl TracerPool {
+    // A trace is only completed by the pool's threads, so there is always at
+    // least one of them.
+    pub fn new(threads: usize) -> Self {
+        let threads = threads.max(1);
+        let shared = Arc::new(PoolShared {
+            state: Mutex::new(PoolState {
+                task: None,
+                epoch: 0,
+                running: 0,
+                panicked: false,
+                shutdown: false,
+            }),
+            start: Condvar::new(),
+            done: Condvar::new(),
+        });
+
+        let threads = (0..threads)
+            .map(|_| {
+                let shared = shared.clone();
+
+                std::thread::spawn(move || shared.run_thread())
+            })
+            .collect();
+
+        Self { shared, threads }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads.len()
+    }
+
+    // Runs the task on every thread in the pool, and blocks until they have
+    // all returned. Returns an error if the task panicked on any thread.
+    pub fn run(&self, task: &(dyn Fn() + Sync)) -> Result<(), ()> {
+        // SAFETY: the task is not accessed after this function returns, as we
+        // wait for every thread to finish running it below.
+        let task: Task = unsafe { core::mem::transmute(task) };
+        let mut state = self.shared.state.lock().unwrap();
+
+        state.task = Some(task);
+        state.epoch += 1;
+        state.running = self.threads.len();
+        state.panicked = false;
+        self.shared.start.notify_all();
+
+        while state.running > 0 {
+            state = self.shared.done.wait(state).unwrap();
+        }
+
+        state.task = None;
+
+        if state.panicked {
+            Err(())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl PoolShared {
+    fn run_thread(&self) {
+        let mut seen_epoch = 0;
+        let mut state = self.state.lock().unwrap();
+
+        loop {
+            if state.shutdown {
+                return;
+            }
+
+            if state.epoch == seen_epoch {
+                state = self.start.wait(state).unwrap();
+                continue;
+            }
+
+            seen_epoch = state.epoch;
+            let task = state.task.expect("pool task is missing");
+            drop(state);
+
+            let result = catch_unwind(AssertUnwindSafe(task));
+
+            state = self.state.lock().unwrap();
+            state.running -= 1;
+            state.panicked |= result.is_err();
+
+            if state.running == 0 {
+                self.done.notify_all();
+            }
+        }
+    }
+}
+
+impl Drop for TracerPool {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().shutdown = true;
+        self.shared.start.notify_all();
+
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use super::forwarding::Forwarding;
+use crate::header::GcMark;
+use core::ptr::NonNull;
+
+unsafe impl Send for WeakJob {}
+unsafe impl Sync for WeakJob {}
+
+// Implemented by the types which hold references that should not keep their
+// referent alive, ie. GcWeak and Ephemeron.
+pub trait WeakRef {
+    fn clear_if_unmarked(&self, mark: GcMark);
+
+    // Updates the reference if its referent was moved by a compacting trace.
+    fn forward(&self, forwarding: &Forwarding);
+}
+
+// A type erased pointer to a weak reference which was reached by a tracer.
+// Once the trace is complete, the reference is cleared if its referent was
+// not marked.
+pub struct WeakJob {
+    ptr: NonNull<()>,
+    dyn_clear: fn(NonNull<()>, GcMark),
+    dyn_forward: fn(NonNull<()>, &Forwarding),
+}
+
+impl WeakJob {
+    pub fn new<W: WeakRef>(weak: &W) -> Self {
+        Self {
+            ptr: NonNull::from(weak).cast(),
+            dyn_clear: clear::<W>,
+            dyn_forward: forward::<W>,
+        }
+    }
+
+    // SAFETY: the weak reference must still be valid, which holds as long as
+    // the GC has not swept since it was traced.
+    pub unsafe fn clear_if_unmarked(&self, mark: GcMark) {
+        (self.dyn_clear)(self.ptr, mark)
+    }
+
+    // SAFETY: same as `clear_if_unmarked`
+    pub unsafe fn forward(&self, forwarding: &Forwarding) {
+        (self.dyn_forward)(self.ptr, forwarding)
+    }
+}
+
+fn clear<W: WeakRef>(ptr: NonNull<()>, mark: GcMark) {
+    let weak: &W = unsafe { ptr.cast().as_ref() };
+
+    weak.clear_if_unmarked(mark);
+}
+
+fn forward<W: WeakRef>(ptr: NonNull<()>, forwarding: &Forwarding) {
+    let weak: &W = unsafe { ptr.cast().as_ref() };
+
+    weak.forward(forwarding);
+}
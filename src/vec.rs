@@ -16,6 +16,10 @@ unsafe impl<'gc, T: GcSync<'gc>> Trace for GcVec<'gc, T> {
             return;
         }
 
+        // Only the first `len` items are traced, so the backing array is
+        // moved without being traced through.
+        self.items.inner().evacuate_untraced(tracer);
+
         if let Some(ptr) = self.items.inner().as_option() {
             tracer.mark(ptr);
         }
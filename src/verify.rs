@@ -0,0 +1,75 @@
+use crate::header::GcMark;
+use alloc::alloc::Layout;
+use alloc::vec::Vec;
+use core::ptr::write_bytes;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+#[cfg(not(feature = "multi_threaded"))]
+use core::cell::RefCell;
+#[cfg(feature = "multi_threaded")]
+use std::sync::Mutex;
+
+// The byte freed values are overwritten with when verifying the heap. It is
+// not a valid mark, so tracing a freed value panics.
+pub const POISON_BYTE: u8 = 0xDE;
+
+// The allocations made while heap verification is enabled, so that the ones
+// which are about to be freed can be poisoned.
+pub struct AllocationLog {
+    #[cfg(feature = "multi_threaded")]
+    allocations: Mutex<Vec<(usize, usize)>>,
+    #[cfg(not(feature = "multi_threaded"))]
+    allocations: RefCell<Vec<(usize, usize)>>,
+}
+
+impl AllocationLog {
+    pub fn new() -> Self {
+        Self {
+            allocations: Default::default(),
+        }
+    }
+
+    #[cfg(feature = "multi_threaded")]
+    fn with<R>(&self, f: impl FnOnce(&mut Vec<(usize, usize)>) -> R) -> R {
+        f(&mut self.allocations.lock().unwrap())
+    }
+
+    #[cfg(not(feature = "multi_threaded"))]
+    fn with<R>(&self, f: impl FnOnce(&mut Vec<(usize, usize)>) -> R) -> R {
+        f(&mut self.allocations.borrow_mut())
+    }
+
+    pub fn record(&self, ptr: *const u8, layout: Layout) {
+        self.with(|allocations| allocations.push((ptr as usize, layout.size())));
+    }
+
+    // Forgets every allocation, such as when verification is disabled, after
+    // which the allocations may be freed without being poisoned.
+    pub fn clear(&self) {
+        self.with(|allocations| allocations.clear());
+    }
+
+    // Overwrites every allocation whose header does not carry the live mark,
+    // and forgets it.
+    //
+    // SAFETY: must only be called once a trace has completed and before the
+    // sweep, while no mutator can run. Each allocation must begin with a
+    // header whose first byte is its mark.
+    pub unsafe fn poison_unmarked(&self, live_mark: GcMark) {
+        let live_mark = u8::from(live_mark);
+
+        self.with(|allocations| {
+            allocations.retain(|&(ptr, size)| {
+                let mark = (*(ptr as *const AtomicU8)).load(Ordering::Acquire);
+
+                if mark == live_mark {
+                    return true;
+                }
+
+                write_bytes(ptr as *mut u8, POISON_BYTE, size);
+
+                false
+            })
+        });
+    }
+}
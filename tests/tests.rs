@@ -1,5 +1,8 @@
 use rand::prelude::*;
-use sandpit::{field, Arena, Gc, GcOpt, GcSync, InnerBarrier, Mutator, Root, Tag, Trace, TraceLeaf};
+use sandpit::{
+    field, AllocError, Arena, CollectionDecision, CollectionPolicy, Config, ConfigError, Ephemeron, Gc, GcOpt, GcSync, GcWeak, Handle, HeapEdge, HeapLimitPolicy,
+    Finalize, InnerBarrier, Metrics, Mutator, Root, StressMode, StressTrigger, Tag, Trace, TraceLeaf, TriggerMetrics,
+};
 
 fn alloc_rand_garbage(mu: &Mutator) {
     let mut rng = rand::thread_rng();
@@ -1378,3 +1381,1457 @@ fn foo_test() {
     arena.mutate(|mu, vec| push_ten_times(mu, vec));
     arena.major_collect();
 }
+
+#[test]
+fn finalizer_runs_once_unreachable() {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use sandpit::Finalize;
+
+    static FINALIZED: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Trace)]
+    struct Resource {
+        id: usize,
+    }
+
+    unsafe impl Finalize for Resource {
+        fn finalize(&mut self) {
+            FINALIZED.fetch_add(self.id, Ordering::SeqCst);
+        }
+    }
+
+    let arena: Arena<Root![GcOpt<'_, Resource>]> =
+        Arena::new(|mu| mu.alloc_finalized(Resource { id: 1 }).into());
+
+    arena.mutate(|mu, _| {
+        mu.alloc_finalized(Resource { id: 10 });
+    });
+
+    arena.major_collect();
+    assert_eq!(FINALIZED.load(Ordering::SeqCst), 10);
+
+    arena.major_collect();
+    assert_eq!(FINALIZED.load(Ordering::SeqCst), 10);
+
+    arena.mutate(|_, root| root.set_none());
+    arena.major_collect();
+    assert_eq!(FINALIZED.load(Ordering::SeqCst), 11);
+}
+
+#[test]
+fn finalizers_run_on_arena_drop() {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use sandpit::Finalize;
+
+    static FINALIZED: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Trace)]
+    struct Resource;
+
+    unsafe impl Finalize for Resource {
+        fn finalize(&mut self) {
+            FINALIZED.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let arena: Arena<Root![Gc<'_, [Gc<'_, Resource>]>]> =
+        Arena::new(|mu| mu.alloc_array_from_fn(5, |_| mu.alloc_finalized(Resource)));
+
+    arena.minor_collect();
+    assert_eq!(FINALIZED.load(Ordering::SeqCst), 0);
+
+    drop(arena);
+    assert_eq!(FINALIZED.load(Ordering::SeqCst), 5);
+}
+
+#[test]
+fn weak_ptr_cleared_once_target_is_freed() {
+    #[derive(Trace)]
+    struct Cache<'gc> {
+        strong: GcOpt<'gc, usize>,
+        weak: GcWeak<'gc, usize>,
+    }
+
+    let arena: Arena<Root![Cache<'_>]> = Arena::new(|mu| {
+        let value = Gc::new(mu, 69);
+
+        Cache {
+            weak: value.downgrade(),
+            strong: value.into(),
+        }
+    });
+
+    arena.major_collect();
+
+    assert_eq!(arena.metrics().get_old_objects_count(), 1);
+
+    arena.mutate(|_, root| {
+        assert_eq!(*root.weak.upgrade().unwrap(), 69);
+
+        root.strong.set_none();
+    });
+
+    arena.major_collect();
+
+    assert_eq!(arena.metrics().get_old_objects_count(), 0);
+
+    arena.mutate(|_, root| assert!(root.weak.upgrade().is_none()));
+}
+
+#[test]
+fn weak_ptr_set_on_old_object_cleared_by_minor_collection() {
+    let arena: Arena<Root![Gc<'_, GcWeak<'_, usize>>]> =
+        Arena::new(|mu| Gc::new(mu, GcWeak::new_none()));
+
+    arena.major_collect();
+
+    arena.mutate(|mu, root| {
+        let young = Gc::new(mu, 42);
+
+        root.write_barrier(mu, |barrier| barrier.set(young));
+
+        assert_eq!(*root.upgrade().unwrap(), 42);
+    });
+
+    arena.minor_collect();
+
+    arena.mutate(|_, root| assert!(root.is_none()));
+}
+
+#[test]
+fn ephemeron_values_traced_once_keys_are_live() {
+    #[derive(Trace)]
+    struct Table<'gc> {
+        // e2 is traced before e1, but its key is only reachable through e1's value
+        e2: Ephemeron<'gc, usize, usize>,
+        e1: Ephemeron<'gc, usize, Gc<'gc, usize>>,
+        key1: Gc<'gc, usize>,
+    }
+
+    let arena: Arena<Root![Table<'_>]> = Arena::new(|mu| {
+        let key1 = Gc::new(mu, 1);
+        let key2 = Gc::new(mu, 2);
+
+        Table {
+            e2: Ephemeron::new(key2.clone(), Gc::new(mu, 20)),
+            e1: Ephemeron::new(key1.clone(), Gc::new(mu, key2)),
+            key1,
+        }
+    });
+
+    arena.major_collect();
+
+    assert_eq!(arena.metrics().get_old_objects_count(), 4);
+
+    arena.mutate(|_, root| {
+        let (key, value) = root.e2.get().unwrap();
+
+        assert_eq!(*key, 2);
+        assert_eq!(*value, 20);
+    });
+}
+
+#[test]
+fn ephemeron_value_does_not_keep_key_alive() {
+    let arena: Arena<Root![Ephemeron<'_, usize, Gc<'_, usize>>]> = Arena::new(|mu| {
+        let key = Gc::new(mu, 1);
+
+        Ephemeron::new(key.clone(), Gc::new(mu, key))
+    });
+
+    arena.major_collect();
+
+    assert_eq!(arena.metrics().get_old_objects_count(), 0);
+
+    arena.mutate(|_, root| {
+        assert!(root.is_none());
+        assert!(root.key().is_none());
+        assert!(root.value().is_none());
+    });
+}
+
+#[test]
+fn handle_keeps_value_alive_outside_of_mutation() {
+    let arena: Arena<Root![()]> = Arena::new(|_| ());
+
+    let handle: Handle<Root![Gc<'_, usize>]> = arena.mutate(|mu, _| {
+        let gc = Gc::new(mu, Gc::new(mu, 42));
+
+        mu.root_handle(&gc)
+    });
+
+    arena.major_collect();
+    arena.minor_collect();
+
+    assert_eq!(arena.metrics().get_old_objects_count(), 2);
+
+    arena.mutate(|mu, _| {
+        assert_eq!(**handle.get(mu), 42);
+    });
+
+    let cloned = handle.clone();
+    drop(handle);
+    arena.major_collect();
+
+    assert_eq!(arena.metrics().get_old_objects_count(), 2);
+
+    drop(cloned);
+    arena.major_collect();
+
+    assert_eq!(arena.metrics().get_old_objects_count(), 0);
+}
+
+#[test]
+fn mutate_returns_values() {
+    let arena: Arena<Root![Gc<'_, usize>]> = Arena::new(|mu| Gc::new(mu, 7));
+
+    let value = arena.mutate(|_, root| **root * 6);
+    assert_eq!(value, 42);
+
+    let result: Result<usize, &str> = arena.try_mutate(|_, root| match **root {
+        7 => Err("unlucky"),
+        n => Ok(n),
+    });
+    assert_eq!(result, Err("unlucky"));
+}
+
+#[test]
+fn oversized_allocations_return_errors() {
+    let arena: Arena<Root![()]> = Arena::new(|_| ());
+
+    arena.mutate(|mu, _| {
+        assert_eq!(
+            mu.try_alloc_array(0u64, usize::MAX).err(),
+            Some(AllocError::TooLarge)
+        );
+        assert_eq!(
+            mu.try_alloc_array_from_fn(usize::MAX / 2, |i| i).err(),
+            Some(AllocError::TooLarge)
+        );
+
+        let s = mu.try_alloc_str("still works").unwrap();
+        assert_eq!(&*s, "still works");
+    });
+}
+
+#[test]
+fn heap_limit_fails_allocations_and_requests_major_collection() {
+    let mut config = Config::default();
+    config.max_heap_bytes = Some(64 * 1024);
+    config.heap_limit_policy = HeapLimitPolicy::Fail;
+
+    let arena: Arena<Root![()]> = Arena::new_with_config(config, |_| ());
+
+    let result = arena.mutate(|mu, _| loop {
+        if let Err(err) = mu.try_alloc_array(0u8, 1024) {
+            break err;
+        }
+    });
+
+    assert_eq!(result, AllocError::HeapLimitExceeded);
+
+    arena.major_collect();
+
+    arena.mutate(|mu, _| {
+        assert!(mu.try_alloc_array(0u8, 1024).is_ok());
+    });
+}
+
+#[test]
+fn heap_limit_callback_can_allow_allocations() {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    fn allow(_: &Metrics, _: usize) -> bool {
+        CALLS.fetch_add(1, Ordering::SeqCst);
+        true
+    }
+
+    let mut config = Config::default();
+    config.max_heap_bytes = Some(16 * 1024);
+    config.heap_limit_policy = HeapLimitPolicy::Callback(allow);
+
+    let arena: Arena<Root![()]> = Arena::new_with_config(config, |_| ());
+
+    arena.mutate(|mu, _| {
+        for _ in 0..64 {
+            assert!(mu.try_alloc_array(0u8, 1024).is_ok());
+        }
+    });
+
+    assert!(CALLS.load(Ordering::SeqCst) > 0);
+}
+
+#[test]
+fn heap_limit_forces_major_collection_before_next_mutation() {
+    let mut config = Config::default();
+    config.monitor_on = false;
+    config.max_heap_bytes = Some(64 * 1024);
+    config.heap_limit_policy = HeapLimitPolicy::Fail;
+
+    let arena: Arena<Root![()]> = Arena::new_with_config(config, |_| ());
+    let major_collections = arena.metrics().get_major_collections();
+
+    let result = arena.mutate(|mu, _| loop {
+        if let Err(err) = mu.try_alloc_array(0u8, 1024) {
+            break err;
+        }
+    });
+
+    assert_eq!(result, AllocError::HeapLimitExceeded);
+
+    // the garbage is freed before the next mutation begins
+    arena.mutate(|mu, _| {
+        assert!(mu.try_alloc_array(0u8, 1024).is_ok());
+    });
+
+    assert_eq!(arena.metrics().get_major_collections(), major_collections + 1);
+}
+
+#[test]
+fn heap_limit_collects_garbage_before_a_mutation_which_fits() {
+    let mut config = Config::default();
+    config.monitor_on = false;
+    config.collection_policy = std::sync::Arc::new(NeverCollect);
+    config.max_heap_bytes = Some(64 * 1024);
+    config.heap_limit_policy = HeapLimitPolicy::Fail;
+
+    let arena: Arena<Root![()]> = Arena::new_with_config(config, |_| ());
+
+    arena.mutate(|mu, _| {
+        for _ in 0..48 {
+            assert!(mu.try_alloc_array(0u8, 1024).is_ok());
+        }
+    });
+
+    // only the garbage of the previous mutation exceeds the limit
+    arena.mutate(|mu, _| {
+        for _ in 0..48 {
+            assert!(mu.try_alloc_array(0u8, 1024).is_ok());
+        }
+    });
+}
+
+#[test]
+fn heap_limit_fails_allocations_within_a_mutation() {
+    let mut config = Config::default();
+    config.monitor_on = false;
+    config.max_heap_bytes = Some(64 * 1024);
+    config.heap_limit_policy = HeapLimitPolicy::Fail;
+
+    let arena: Arena<Root![Gc<'_, [GcOpt<'_, [u8]>]>]> =
+        Arena::new_with_config(config, |mu| mu.alloc_array_from_fn(128, |_| GcOpt::new_none()));
+
+    // the values are kept alive, so the limit must hold within the mutation
+    let result = arena.mutate(|mu, root| {
+        let result = (0..root.len()).try_for_each(|i| {
+            let bytes = mu.try_alloc_array(0u8, 1024)?;
+
+            root.write_barrier(mu, |barrier| barrier.at(i).set(bytes));
+
+            Ok(())
+        });
+
+        let metrics = arena.metrics();
+        assert!(metrics.get_prev_arena_size() + metrics.get_allocated_bytes() <= 64 * 1024);
+
+        result
+    });
+
+    assert_eq!(result, Err(AllocError::HeapLimitExceeded));
+
+    arena.mutate(|mu, root| {
+        root.write_barrier(mu, |barrier| {
+            for i in 0..root.len() {
+                barrier.at(i).set(GcOpt::new_none());
+            }
+        });
+    });
+
+    // the cleared values are freed before the next mutation begins
+    arena.mutate(|mu, _| {
+        assert!(mu.try_alloc_array(0u8, 1024).is_ok());
+    });
+}
+
+#[derive(Trace)]
+struct CompactRoot<'gc> {
+    items: Gc<'gc, [Gc<'gc, usize>]>,
+    tagged: GcVec<'gc, Tagged<'gc, TestTag>>,
+    weak: GcWeak<'gc, usize>,
+    ephemeron: Ephemeron<'gc, usize, usize>,
+}
+
+fn verify_compact_root(root: &CompactRoot) {
+    for (i, item) in root.items.iter().enumerate() {
+        assert_eq!(**item, i);
+    }
+
+    for i in 0..root.tagged.len() {
+        let ptr = TestTag::get_ptr(root.tagged.get_idx(i).unwrap()).unwrap();
+
+        assert_eq!(*ptr, i);
+    }
+
+    assert_eq!(*root.weak.upgrade().unwrap(), 0);
+
+    let (key, value) = root.ephemeron.get().unwrap();
+    assert_eq!(*key, 1);
+    assert_eq!(*value, 42);
+}
+
+#[test]
+fn compaction_moves_values_and_updates_pointers() {
+    let mut config = Config::default();
+    config.compaction_on = true;
+
+    let arena: Arena<Root![CompactRoot<'_>]> = Arena::new_with_config(config, |mu| {
+        // interleave garbage so that live values are spread across the heap
+        let items = mu.alloc_array_from_fn(100, |i| {
+            mu.alloc_array(0usize, 100);
+            Gc::new(mu, i)
+        });
+        let tagged = GcVec::new(mu);
+
+        for i in 0..100 {
+            mu.alloc_array(0usize, 100);
+            tagged.push(mu, TestTag::from_ptr(Gc::new(mu, i)));
+        }
+
+        alloc_rand_garbage(mu);
+
+        CompactRoot {
+            weak: items[0].downgrade(),
+            ephemeron: Ephemeron::new(items[1].clone(), Gc::new(mu, 42)),
+            items,
+            tagged,
+        }
+    });
+
+    let address = |arena: &Arena<Root![CompactRoot<'_>]>| {
+        arena.mutate(|_, root| &*root.items[50] as *const usize as usize)
+    };
+
+    let before = address(&arena);
+
+    arena.major_collect();
+
+    assert_ne!(before, address(&arena));
+
+    arena.mutate(|mu, root| {
+        verify_compact_root(root);
+        alloc_rand_garbage(mu);
+    });
+
+    arena.minor_collect();
+    arena.major_collect();
+
+    arena.mutate(|_, root| verify_compact_root(root));
+}
+
+#[test]
+fn compaction_leaves_densely_occupied_blocks_in_place() {
+    let mut config = Config::default();
+    config.compaction_on = true;
+
+    let arena: Arena<Root![Gc<'_, [Gc<'_, [usize; 8]>]>]> =
+        Arena::new_with_config(config, |mu| {
+            mu.alloc_array_from_fn(1000, |i| Gc::new(mu, [i; 8]))
+        });
+
+    let address = |arena: &Arena<Root![Gc<'_, [Gc<'_, [usize; 8]>]>]>| {
+        arena.mutate(|_, root| &*root[500] as *const [usize; 8] as usize)
+    };
+
+    let before = address(&arena);
+
+    arena.major_collect();
+
+    assert_eq!(before, address(&arena));
+    arena.mutate(|_, root| {
+        for (i, item) in root.iter().enumerate() {
+            assert_eq!(**item, [i; 8]);
+        }
+    });
+}
+
+#[test]
+fn compaction_is_skipped_while_viewing() {
+    let mut config = Config::default();
+    config.compaction_on = true;
+
+    let arena: Arena<Root![CompactRoot<'_>]> = Arena::new_with_config(config, |mu| {
+        let items = mu.alloc_array_from_fn(100, |i| {
+            mu.alloc_array(0usize, 100);
+            Gc::new(mu, i)
+        });
+
+        CompactRoot {
+            weak: items[0].downgrade(),
+            ephemeron: Ephemeron::new(items[1].clone(), Gc::new(mu, 42)),
+            items,
+            tagged: GcVec::new(mu),
+        }
+    });
+
+    let address = |arena: &Arena<Root![CompactRoot<'_>]>| {
+        arena.mutate(|_, root| &*root.items[50] as *const usize as usize)
+    };
+
+    let before = address(&arena);
+
+    arena.view(|root| {
+        let item = &*root.items[50];
+
+        arena.major_collect();
+
+        assert_eq!(*item, 50);
+        assert_eq!(before, item as *const usize as usize);
+    });
+
+    arena.major_collect();
+
+    assert_ne!(before, address(&arena));
+}
+
+#[test]
+fn compaction_forwards_handles_and_finalizers() {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static FINALIZED: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Trace)]
+    struct Resource(usize);
+
+    unsafe impl Finalize for Resource {
+        fn finalize(&mut self) {
+            FINALIZED.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let mut config = Config::default();
+    config.compaction_on = true;
+
+    let arena: Arena<Root![()]> = Arena::new_with_config(config, |_| ());
+
+    let handle: Handle<Root![Resource]> = arena.mutate(|mu, _| {
+        mu.alloc_finalized(Resource(1));
+        alloc_rand_garbage(mu);
+
+        mu.root_handle(&mu.alloc_finalized(Resource(2)))
+    });
+
+    arena.major_collect();
+
+    assert_eq!(FINALIZED.load(Ordering::SeqCst), 1);
+
+    arena.mutate(|mu, _| assert_eq!(handle.get(mu).0, 2));
+
+    arena.major_collect();
+    drop(handle);
+    arena.major_collect();
+
+    assert_eq!(FINALIZED.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn many_tracers_share_work() {
+    let mut config = Config::default();
+    config.tracer_threads = 8;
+    config.trace_chunk_size = 1;
+    config.trace_share_min = 1;
+
+    let arena: Arena<Root![Gc<'_, [Gc<'_, [Gc<'_, usize>]>]>]> =
+        Arena::new_with_config(config, |mu| {
+            mu.alloc_array_from_fn(100, |i| {
+                mu.alloc_array_from_fn(100, |k| Gc::new(mu, (i * 100) + k))
+            })
+        });
+
+    for _ in 0..3 {
+        arena.major_collect();
+        arena.mutate(|mu, _| alloc_rand_garbage(mu));
+        arena.minor_collect();
+    }
+
+    arena.mutate(|_, root| {
+        for (i, inner) in root.iter().enumerate() {
+            for (k, value) in inner.iter().enumerate() {
+                assert_eq!(**value, (i * 100) + k);
+            }
+        }
+    });
+}
+
+#[test]
+fn concurrent_sweep_keeps_values_allocated_during_sweep() {
+    let mut config = Config::default();
+    config.concurrent_sweep = true;
+
+    let arena: Arena<Root![GcVec<'_, Gc<'_, usize>>]> =
+        Arena::new_with_config(config, |mu| GcVec::new(mu));
+
+    for _ in 0..5 {
+        arena.mutate(|mu, _| {
+            for _ in 0..10 {
+                alloc_rand_garbage(mu);
+            }
+        });
+
+        arena.major_collect();
+
+        // the garbage may still be being swept while these are allocated
+        arena.mutate(|mu, root| {
+            for i in 0..1000 {
+                root.push(mu, Gc::new(mu, i));
+            }
+        });
+
+        arena.major_collect();
+
+        arena.mutate(|_, root| {
+            for i in 0..root.len() {
+                assert_eq!(*root.get_idx(i).unwrap(), i % 1000);
+            }
+        });
+    }
+}
+
+#[test]
+fn concurrent_sweep_keeps_live_values() {
+    let mut config = Config::default();
+    config.concurrent_sweep = true;
+
+    let arena: Arena<Root![Gc<'_, [Gc<'_, usize>]>]> =
+        Arena::new_with_config(config, |mu| mu.alloc_array_from_fn(100, |i| Gc::new(mu, i)));
+
+    for _ in 0..5 {
+        arena.mutate(|mu, root| {
+            alloc_rand_garbage(mu);
+
+            for (i, value) in root.iter().enumerate() {
+                assert_eq!(**value, i);
+            }
+        });
+
+        arena.major_collect();
+        arena.minor_collect();
+    }
+
+    // the arena is dropped while its last sweep may still be running
+}
+
+#[cfg(feature = "multi_threaded")]
+#[test]
+fn safepoint_keeps_declared_values_alive() {
+    let mut config = Config::default();
+    config.monitor_wait_time = 1;
+
+    let arena: Arena<Root![Gc<'_, usize>]> = Arena::new_with_config(config, |mu| Gc::new(mu, 42));
+
+    arena.major_collect();
+
+    let collections =
+        arena.metrics().get_minor_collections() + arena.metrics().get_major_collections();
+
+    arena.mutate(|mu, root| {
+        let local = mu.alloc_array_from_fn(1000, |k| Gc::new(mu, k));
+
+        // allocate garbage until the monitor begins a collection and waits on
+        // this mutator
+        loop {
+            alloc_rand_garbage(mu);
+
+            if unsafe { mu.safepoint(&local) } {
+                break;
+            }
+        }
+
+        for (k, value) in local.iter().enumerate() {
+            assert_eq!(**value, k);
+        }
+
+        let after = mu.alloc_array_from_fn(1000, |k| Gc::new(mu, k));
+
+        for (k, value) in after.iter().enumerate() {
+            assert_eq!(**value, k);
+        }
+
+        assert_eq!(**root, 42);
+    });
+
+    assert!(
+        arena.metrics().get_minor_collections() + arena.metrics().get_major_collections()
+            > collections
+    );
+}
+
+#[cfg(feature = "multi_threaded")]
+#[test]
+fn safepoint_applies_config_updates() {
+    let mut config = Config::default();
+    config.monitor_wait_time = 1;
+
+    let arena: Arena<Root![()]> = Arena::new_with_config(config, |_| ());
+
+    arena.mutate(|mu, _| {
+        arena.update_config(|config| config.max_heap_bytes = Some(1)).unwrap();
+
+        // the mutation continues to use the config it began with
+        assert!(mu.try_alloc_array(0u8, 1024).is_ok());
+
+        loop {
+            alloc_rand_garbage(mu);
+
+            if unsafe { mu.safepoint(&()) } {
+                break;
+            }
+        }
+
+        assert_eq!(mu.try_alloc_array(0u8, 1024).err(), Some(AllocError::HeapLimitExceeded));
+    });
+}
+
+#[cfg(feature = "multi_threaded")]
+static SLOW_TRACE: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+// Holds the trace in progress while SLOW_TRACE is set.
+#[cfg(feature = "multi_threaded")]
+struct SlowTrace;
+
+#[cfg(feature = "multi_threaded")]
+unsafe impl Trace for SlowTrace {
+    const IS_LEAF: bool = false;
+
+    fn trace(&self, _tracer: &mut sandpit::Tracer) {
+        while SLOW_TRACE.load(std::sync::atomic::Ordering::SeqCst) {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+}
+
+// Starts a major collection on the monitor thread while SLOW_TRACE is set.
+#[cfg(feature = "multi_threaded")]
+#[derive(Debug)]
+struct MajorWhileSlow;
+
+#[cfg(feature = "multi_threaded")]
+impl CollectionPolicy for MajorWhileSlow {
+    fn decide(&self, _metrics: &TriggerMetrics, _config: &Config) -> CollectionDecision {
+        if SLOW_TRACE.load(std::sync::atomic::Ordering::SeqCst) {
+            CollectionDecision::Major
+        } else {
+            CollectionDecision::None
+        }
+    }
+}
+
+#[cfg(feature = "multi_threaded")]
+#[test]
+fn mutators_are_paced_by_allocation_during_the_trace() {
+    use std::sync::atomic::Ordering;
+
+    let mut config = Config::default();
+    config.collection_policy = std::sync::Arc::new(MajorWhileSlow);
+    config.monitor_wait_time = 1;
+    config.tracer_threads = 1;
+
+    let arena: Arena<Root![(SlowTrace, Gc<'_, [usize]>)]> =
+        Arena::new_with_config(config, |mu| (SlowTrace, mu.alloc_array_from_fn(10_000, |k| k)));
+
+    arena.major_collect();
+
+    let headroom_bytes = arena.metrics().get_prev_arena_size() as usize;
+
+    let (light_yields, heavy_yields) = arena.mutate(|mu, _| {
+        // allocations made before the trace begins must not count towards
+        // pacing
+        mu.alloc_array_from_fn(headroom_bytes / 8, |k| k);
+
+        SLOW_TRACE.store(true, Ordering::SeqCst);
+
+        while arena.metrics().get_state() != 1 {
+            std::thread::yield_now();
+        }
+
+        // a mutator which barely allocates is not paced
+        for _ in 0..100 {
+            Gc::new(mu, 0usize);
+            mu.gc_yield();
+        }
+
+        let light_yields = arena.metrics().get_timeslice_yields();
+
+        // a mutator which allocates past the headroom is, once the next
+        // slice begins
+        mu.alloc_array_from_fn(headroom_bytes / 8, |k| k);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        mu.gc_yield();
+
+        let heavy_yields = arena.metrics().get_timeslice_yields();
+
+        SLOW_TRACE.store(false, Ordering::SeqCst);
+
+        (light_yields, heavy_yields)
+    });
+
+    assert_eq!(light_yields, 0);
+    assert!(heavy_yields > 0);
+}
+
+#[derive(Debug)]
+struct NeverCollect;
+
+impl CollectionPolicy for NeverCollect {
+    fn decide(&self, _metrics: &TriggerMetrics, _config: &Config) -> CollectionDecision {
+        CollectionDecision::None
+    }
+}
+
+#[derive(Debug)]
+struct AlwaysMinor;
+
+impl CollectionPolicy for AlwaysMinor {
+    fn decide(&self, _metrics: &TriggerMetrics, _config: &Config) -> CollectionDecision {
+        CollectionDecision::Minor
+    }
+}
+
+#[test]
+fn collection_policy_decides_collections() {
+    let mut config = Config::default();
+    config.monitor_wait_time = 1;
+    config.collection_policy = std::sync::Arc::new(NeverCollect);
+
+    let arena: Arena<Root![()]> = Arena::new_with_config(config, |_| ());
+
+    for _ in 0..10 {
+        arena.mutate(|mu, _| {
+            alloc_rand_garbage(mu);
+            assert!(!mu.gc_yield());
+        });
+    }
+
+    std::thread::sleep(std::time::Duration::from_millis(10));
+
+    assert_eq!(arena.metrics().get_major_collections(), 0);
+    assert_eq!(arena.metrics().get_minor_collections(), 0);
+
+    // explicit collections are unaffected by the policy
+    arena.major_collect();
+    assert_eq!(arena.metrics().get_major_collections(), 1);
+}
+
+#[test]
+fn paused_monitor_triggers_no_collections() {
+    let mut config = Config::default();
+    config.monitor_wait_time = 1;
+    config.collection_policy = std::sync::Arc::new(AlwaysMinor);
+
+    let arena: Arena<Root![()]> = Arena::new_with_config(config, |_| ());
+
+    arena.with_gc_disabled(|| {
+        for _ in 0..10 {
+            arena.mutate(|mu, _| alloc_rand_garbage(mu));
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        assert_eq!(arena.metrics().get_minor_collections(), 0);
+    });
+
+    arena.stop_monitor();
+    arena.mutate(|mu, _| alloc_rand_garbage(mu));
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    assert_eq!(arena.metrics().get_minor_collections(), 0);
+
+    arena.start_monitor();
+    arena.mutate(|mu, _| alloc_rand_garbage(mu));
+
+    while arena.metrics().get_minor_collections() == 0 {
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn update_config_on_live_arena() {
+    let arena: Arena<Root![Gc<'_, [Gc<'_, usize>]>]> =
+        Arena::new(|mu| mu.alloc_array_from_fn(100, |i| Gc::new(mu, i)));
+
+    arena
+        .update_config(|config| {
+            config.tracer_threads = 4;
+            config.concurrent_sweep = true;
+        })
+        .unwrap();
+
+    assert_eq!(arena.config().tracer_threads, 4);
+    assert!(arena.config().concurrent_sweep);
+
+    for threads in [1, 2, 3] {
+        arena.mutate(|mu, _| {
+            alloc_rand_garbage(mu);
+
+            arena
+                .update_config(|config| config.tracer_threads = threads)
+                .unwrap();
+        });
+
+        arena.major_collect();
+        arena.minor_collect();
+
+        assert_eq!(arena.config().tracer_threads, threads);
+        arena.mutate(|_, root| {
+            for (i, value) in root.iter().enumerate() {
+                assert_eq!(**value, i);
+            }
+        });
+    }
+}
+
+#[test]
+#[should_panic(expected = "invalid config")]
+fn new_with_config_rejects_invalid_config() {
+    let mut config = Config::default();
+    config.tracer_threads = 0;
+
+    let _arena: Arena<Root![()]> = Arena::new_with_config(config, |_| ());
+}
+
+#[test]
+fn update_config_rejects_invalid_config() {
+    let arena: Arena<Root![()]> = Arena::new(|_| ());
+
+    let result = arena.update_config(|config| {
+        config.trace_chunk_size = 50;
+        config.tracer_threads = 0;
+    });
+
+    assert_eq!(result, Err(ConfigError::NoTracerThreads));
+    assert_ne!(arena.config().tracer_threads, 0);
+    assert_ne!(arena.config().trace_chunk_size, 50);
+
+    arena.major_collect();
+    arena.mutate(|mu, _| alloc_rand_garbage(mu));
+}
+
+#[test]
+fn config_builder_validates_settings() {
+    use sandpit::{ConfigBuilder, ConfigError};
+
+    for preset in [
+        Config::default(),
+        Config::low_latency(),
+        Config::throughput(),
+        Config::single_core(),
+    ] {
+        assert!(ConfigBuilder::from(preset).build().is_ok());
+    }
+
+    let config = Config::builder()
+        .tracer_threads(3)
+        .trace_share_ratio(1.0)
+        .max_heap_bytes(Some(1 << 20))
+        .build()
+        .unwrap();
+
+    assert_eq!(config.tracer_threads, 3);
+    assert_eq!(config.max_heap_bytes, Some(1 << 20));
+
+    let errors = [
+        (Config::builder().tracer_threads(0), ConfigError::NoTracerThreads),
+        (Config::builder().trace_chunk_size(0), ConfigError::EmptyTraceChunk),
+        (Config::builder().trace_share_ratio(-0.1), ConfigError::TraceShareRatioOutOfRange),
+        (Config::builder().trace_share_ratio(f32::NAN), ConfigError::TraceShareRatioOutOfRange),
+        (Config::builder().monitor_max_old_growth_rate(0.5), ConfigError::MaxOldGrowthRateTooSmall),
+        (Config::builder().monitor_arena_size_ratio_trigger(0.9), ConfigError::ArenaSizeRatioTriggerTooSmall),
+        (Config::builder().collector_max_headroom_ratio(-1.0), ConfigError::NegativeHeadroom),
+        (Config::builder().collector_timeslice_size(0.0), ConfigError::InvalidTimeslice),
+        (Config::builder().collector_slice_min(10.0), ConfigError::InvalidTimeslice),
+    ];
+
+    for (builder, error) in errors {
+        assert_eq!(builder.build().unwrap_err(), error);
+    }
+}
+
+#[test]
+fn config_env_overrides() {
+    use sandpit::ConfigError;
+
+    std::env::set_var("SANDPIT_TRACER_THREADS", "5");
+    std::env::set_var("SANDPIT_MAX_HEAP", "1048576");
+    std::env::set_var("SANDPIT_MONITOR_WAIT_MS", " 25 ");
+    std::env::set_var("SANDPIT_CONCURRENT_SWEEP", "1");
+
+    let config = Config::from_env().unwrap();
+
+    assert_eq!(config.tracer_threads, 5);
+    assert_eq!(config.max_heap_bytes, Some(1048576));
+    assert_eq!(config.monitor_wait_time, 25);
+    assert!(config.concurrent_sweep);
+    assert_eq!(config.trace_chunk_size, Config::default().trace_chunk_size);
+
+    std::env::set_var("SANDPIT_MAX_HEAP", "none");
+    assert_eq!(Config::from_env().unwrap().max_heap_bytes, None);
+
+    std::env::set_var("SANDPIT_MONITOR_WAIT_MS", "soon");
+    assert_eq!(
+        Config::from_env().unwrap_err(),
+        ConfigError::InvalidEnvVar("SANDPIT_MONITOR_WAIT_MS")
+    );
+
+    std::env::remove_var("SANDPIT_MONITOR_WAIT_MS");
+    std::env::set_var("SANDPIT_TRACER_THREADS", "0");
+    assert_eq!(Config::from_env().unwrap_err(), ConfigError::NoTracerThreads);
+
+    for var in [
+        "SANDPIT_TRACER_THREADS",
+        "SANDPIT_MAX_HEAP",
+        "SANDPIT_CONCURRENT_SWEEP",
+    ] {
+        std::env::remove_var(var);
+    }
+}
+
+#[test]
+fn event_listener_receives_collection_events() {
+    use sandpit::{CollectionKind, GcEvent};
+    use std::sync::{Arc, Mutex};
+
+    let mut config = Config::default();
+    config.monitor_on = false;
+
+    let arena: Arena<Root![Gc<'_, [Gc<'_, usize>]>]> =
+        Arena::new_with_config(config, |mu| mu.alloc_array_from_fn(100, |i| Gc::new(mu, i)));
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let listener_events = events.clone();
+
+    arena.add_event_listener(Arc::new(move |event: &GcEvent| {
+        listener_events.lock().unwrap().push(*event);
+    }));
+
+    arena.major_collect();
+    arena.minor_collect();
+
+    let events = events.lock().unwrap();
+    let kinds: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            GcEvent::CollectionStarted { kind, .. } => Some(("started", *kind)),
+            GcEvent::CollectionFinished { kind, .. } => Some(("finished", *kind)),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        kinds,
+        [
+            ("started", CollectionKind::Major),
+            ("finished", CollectionKind::Major),
+            ("started", CollectionKind::Minor),
+            ("finished", CollectionKind::Minor),
+        ]
+    );
+
+    let major_marked = events.iter().find_map(|event| match event {
+        GcEvent::CollectionFinished {
+            kind: CollectionKind::Major,
+            objects_marked,
+            bytes_after,
+            ..
+        } => Some((*objects_marked, *bytes_after)),
+        _ => None,
+    });
+
+    let (objects_marked, bytes_after) = major_marked.unwrap();
+    assert!(objects_marked >= 101);
+    assert!(bytes_after > 0);
+
+    let sweeps = events
+        .iter()
+        .filter(|event| matches!(event, GcEvent::SweepFinished { .. }))
+        .count();
+    assert_eq!(sweeps, 2);
+}
+
+#[test]
+fn metrics_snapshot_records_collection_times() {
+    let mut config = Config::default();
+    config.monitor_on = false;
+
+    let arena: Arena<Root![Gc<'_, [Gc<'_, usize>]>]> =
+        Arena::new_with_config(config, |mu| mu.alloc_array_from_fn(100, |i| Gc::new(mu, i)));
+
+    for _ in 0..5 {
+        arena.mutate(|mu, _| alloc_rand_garbage(mu));
+        arena.major_collect();
+        arena.minor_collect();
+        arena.minor_collect();
+    }
+
+    let snapshot = arena.metrics_snapshot();
+
+    assert_eq!(snapshot.major_collections, 5);
+    assert_eq!(snapshot.minor_collections, 10);
+    assert!(snapshot.major_collect_avg_time > std::time::Duration::ZERO);
+
+    for (times, count) in [
+        (snapshot.major_collection_times, 5),
+        (snapshot.major_pause_times, 5),
+        (snapshot.minor_collection_times, 10),
+        (snapshot.minor_pause_times, 10),
+    ] {
+        assert_eq!(times.count, count);
+        assert!(times.p50 <= times.p95);
+        assert!(times.p95 <= times.p99);
+        assert!(times.p99 <= times.max);
+        assert!(times.max > std::time::Duration::ZERO);
+    }
+
+    assert!(snapshot.major_pause_times.max <= snapshot.major_collection_times.max);
+    assert_eq!(snapshot.arena_size, arena.metrics().get_arena_size());
+}
+
+#[test]
+fn heap_census_counts_live_values_by_type() {
+    let mut config = Config::default();
+    config.monitor_on = false;
+
+    let arena: Arena<Root![Gc<'_, [Gc<'_, usize>]>]> =
+        Arena::new_with_config(config, |mu| mu.alloc_array_from_fn(100, |i| Gc::new(mu, i)));
+
+    arena.mutate(|mu, _| {
+        for i in 0..1000u64 {
+            Gc::new(mu, i);
+        }
+    });
+
+    let census = arena.heap_census();
+    let usizes = census.get(std::any::type_name::<usize>()).unwrap();
+
+    assert_eq!(usizes.count, 100);
+    assert!(usizes.bytes >= 100 * std::mem::size_of::<usize>() as u64);
+    assert!(census.get(std::any::type_name::<u64>()).is_none());
+    assert_eq!(census.types().len(), 2);
+    assert_eq!(census.total_count(), 101);
+    assert!(census.types()[0].bytes >= census.types()[1].bytes);
+    assert_eq!(arena.metrics().get_major_collections(), 1);
+
+    // the census is only taken by the collection it was requested for
+    arena.major_collect();
+    assert_eq!(arena.heap_census().total_count(), 101);
+}
+
+#[test]
+fn heap_dump_records_object_graph() {
+    let mut config = Config::default();
+    config.monitor_on = false;
+    config.compaction_on = true;
+
+    // both halves of the root share a single array of boxed values
+    let arena: Arena<Root![(Gc<'_, [Gc<'_, usize>]>, Gc<'_, [Gc<'_, usize>]>)]> =
+        Arena::new_with_config(config, |mu| {
+            let array = mu.alloc_array_from_fn(3, |i| Gc::new(mu, i));
+
+            (array.clone(), array)
+        });
+
+    arena.mutate(|mu, _| {
+        Gc::new(mu, 0u64);
+    });
+
+    let dump = arena.heap_dump();
+
+    assert_eq!(dump.nodes.len(), 4);
+    assert_eq!(dump.edges.len(), 4);
+    assert!(dump.nodes.windows(2).all(|w| w[0].address < w[1].address));
+
+    arena.view(|root| {
+        let array = root.0.as_ptr() as usize;
+        let root_edges: Vec<_> = dump.edges.iter().filter(|e| e.from.is_none()).collect();
+
+        assert_eq!(root_edges.len(), 1);
+        assert_eq!(root_edges[0].to, array);
+
+        for elem in root.0.iter() {
+            let addr = &**elem as *const usize as usize;
+            let node = dump.nodes.iter().find(|n| n.address == addr).unwrap();
+
+            assert_eq!(node.type_name, std::any::type_name::<usize>());
+            assert!(node.size >= std::mem::size_of::<usize>());
+            assert!(dump.edges.contains(&HeapEdge {
+                from: Some(array),
+                to: addr
+            }));
+        }
+    });
+
+    let json = dump.to_json();
+    assert!(json.contains("\"nodes\""));
+    assert!(json.contains("\"from\": null"));
+    assert_eq!(json.matches("\"address\"").count(), 4);
+
+    let dot = dump.to_dot();
+    assert!(dot.starts_with("digraph heap {"));
+    assert_eq!(dot.matches(" -> ").count(), 4);
+}
+
+#[test]
+fn heap_dump_attributes_ephemeron_values_to_their_owner() {
+    let mut config = Config::default();
+    config.monitor_on = false;
+
+    // the key is only reached through a chain of pointers, so it is likely
+    // to be unmarked when the ephemeron is traced
+    let arena: Arena<Root![(Gc<'_, Gc<'_, Gc<'_, usize>>>, Gc<'_, Ephemeron<'_, usize, usize>>)]> =
+        Arena::new_with_config(config, |mu| {
+            let key = Gc::new(mu, 1usize);
+            let ephemeron = Gc::new(mu, Ephemeron::new(key.clone(), Gc::new(mu, 2usize)));
+
+            (Gc::new(mu, Gc::new(mu, key)), ephemeron)
+        });
+
+    let dump = arena.heap_dump();
+
+    arena.view(|root| {
+        let owner = &*root.1 as *const Ephemeron<'_, usize, usize> as usize;
+        let value = &*root.1.value().unwrap() as *const usize as usize;
+
+        assert!(dump.edges.contains(&HeapEdge {
+            from: Some(owner),
+            to: value
+        }));
+        assert!(!dump.edges.contains(&HeapEdge {
+            from: None,
+            to: value
+        }));
+    });
+}
+
+#[test]
+fn retention_path_finds_shortest_chain_from_root() {
+    let mut config = Config::default();
+    config.monitor_on = false;
+
+    // the inner value is reachable both through a chain of three pointers and
+    // directly through the second half of the root
+    let arena: Arena<Root![(Gc<'_, Gc<'_, Gc<'_, usize>>>, Gc<'_, Gc<'_, usize>>)]> =
+        Arena::new_with_config(config, |mu| {
+            let inner = Gc::new(mu, 7usize);
+            let middle = Gc::new(mu, inner.clone());
+
+            (Gc::new(mu, middle), Gc::new(mu, inner))
+        });
+
+    arena.view(|root| {
+        let middle: &Gc<'_, Gc<'_, usize>> = &root.0;
+        let path = arena.retention_path(middle).unwrap();
+
+        assert_eq!(path.nodes.len(), 2);
+        assert_eq!(path.nodes[0].address, &*root.0 as *const _ as usize);
+        assert_eq!(path.nodes[1].address, &**root.0 as *const _ as usize);
+
+        let path = arena.retention_path(&**root.0).unwrap();
+
+        assert_eq!(path.nodes.len(), 2);
+        assert_eq!(path.nodes[0].address, &*root.1 as *const _ as usize);
+        assert_eq!(path.nodes[1].type_name, std::any::type_name::<usize>());
+        assert!(path.to_string().starts_with("root -> "));
+        assert_eq!(path.to_string().matches(" -> ").count(), 2);
+    });
+
+    assert!(arena.heap_dump().retention_path(1).is_none());
+}
+
+#[test]
+fn verify_heap_accepts_correct_mutations() {
+    let mut config = Config::default();
+    config.monitor_on = false;
+    config.compaction_on = true;
+    config.verify_heap = true;
+
+    let arena: Arena<Root![GcVec<'_, Tagged<'_, TestTag>>]> =
+        Arena::new_with_config(config, |mu| GcVec::new(mu));
+
+    for _ in 0..5 {
+        arena.mutate(|mu, root| {
+            for i in 0..100usize {
+                root.push(mu, TestTag::from_ptr(Gc::new(mu, i)));
+                Gc::new(mu, i);
+            }
+        });
+
+        arena.minor_collect();
+        arena.major_collect();
+    }
+
+    arena.mutate(|_, root| {
+        assert_eq!(root.len(), 500);
+
+        for i in 0..root.len() {
+            let gc = TestTag::get_ptr(root.get_idx(i).unwrap()).unwrap();
+
+            assert_eq!(*gc, i % 100);
+        }
+    });
+}
+
+#[test]
+fn verify_heap_poisons_freed_values() {
+    let mut config = Config::default();
+    config.monitor_on = false;
+    config.verify_heap = true;
+
+    let arena: Arena<Root![GcVec<'_, Tagged<'_, TestTag>>]> =
+        Arena::new_with_config(config, |mu| GcVec::new(mu));
+
+    // the freed value's address is stored untraced, next to a live value
+    // which keeps its block from being released
+    arena.mutate(|mu, root| {
+        root.push(mu, TestTag::from_ptr(Gc::new(mu, 1usize)));
+
+        let freed = Gc::new(mu, usize::MAX);
+        let address = &*freed as *const usize as usize;
+
+        root.push(mu, Tagged::from_raw(address, TestTag::Raw));
+    });
+
+    arena.major_collect();
+
+    arena.mutate(|_, root| {
+        let address = Tagged::<TestTag>::strip_tag(root.get_idx(1).unwrap().get_raw());
+        let value = unsafe { std::ptr::read_volatile(address as *const usize) };
+
+        assert_eq!(value, usize::from_ne_bytes([0xDE; std::mem::size_of::<usize>()]));
+        assert_eq!(*TestTag::get_ptr(root.get_idx(0).unwrap()).unwrap(), 1);
+    });
+}
+
+#[test]
+#[should_panic(expected = "heap verification failed")]
+fn verify_heap_detects_missing_write_barrier() {
+    let mut config = Config::default();
+    config.monitor_on = false;
+    config.verify_heap = true;
+
+    // the failed collection leaves the arena unusable, so it is leaked
+    // rather than dropped
+    let arena: &Arena<Root![Gc<'_, Tagged<'_, TestTag>>]> = Box::leak(Box::new(
+        Arena::new_with_config(config, |mu| Gc::new(mu, Tagged::from_raw(0, TestTag::Raw))),
+    ));
+
+    // marks the tagged pointer's allocation as old
+    arena.major_collect();
+
+    arena.mutate(|mu, root| {
+        let new = TestTag::from_ptr(Gc::new(mu, 123usize));
+
+        // SAFETY: not safe, the write barrier is deliberately skipped
+        unsafe { root.set(new.get_raw()) };
+    });
+
+    // the old allocation isn't retraced, so the new value is never marked
+    arena.minor_collect();
+}
+
+fn stress_config(stress_mode: StressMode) -> Config {
+    let mut config = Config::default();
+    config.monitor_on = false;
+    config.verify_heap = true;
+    config.stress_mode = Some(stress_mode);
+    config
+}
+
+#[test]
+fn stress_mode_collects_at_every_mutate_exit() {
+    let config = stress_config(StressMode {
+        request_on_alloc: false,
+        request_on_yield: false,
+        request_on_mutate_exit: true,
+        trigger: StressTrigger::Always,
+    });
+
+    let arena: Arena<Root![Gc<'_, usize>]> = Arena::new_with_config(config, |mu| Gc::new(mu, 0));
+
+    for _ in 0..4 {
+        arena.mutate(|mu, _| {
+            Gc::new(mu, 1usize);
+            assert!(!mu.gc_yield());
+        });
+    }
+
+    // collections alternate between minor and major
+    assert_eq!(arena.metrics().get_minor_collections(), 2);
+    assert_eq!(arena.metrics().get_major_collections(), 2);
+}
+
+#[test]
+fn stress_mode_on_alloc_requests_yield() {
+    let config = stress_config(StressMode {
+        request_on_alloc: true,
+        request_on_yield: false,
+        request_on_mutate_exit: false,
+        trigger: StressTrigger::Always,
+    });
+
+    let arena: Arena<Root![GcVec<'_, Gc<'_, usize>>]> =
+        Arena::new_with_config(config, |mu| GcVec::new(mu));
+
+    arena.major_collect();
+
+    // the requested collection runs once the mutation exits
+    for i in 0..2 {
+        arena.mutate(|mu, root| {
+            assert!(!mu.gc_yield());
+            root.push(mu, Gc::new(mu, i));
+            assert!(mu.gc_yield());
+        });
+    }
+
+    assert_eq!(arena.metrics().get_minor_collections(), 1);
+    assert_eq!(arena.metrics().get_major_collections(), 2);
+
+    arena.mutate(|_, root| {
+        assert_eq!(*root.get_idx(0).unwrap(), 0);
+        assert_eq!(*root.get_idx(1).unwrap(), 1);
+    });
+}
+
+#[test]
+fn stress_mode_random_is_reproducible() {
+    let run = |seed| {
+        let config = stress_config(StressMode::random(seed, 3));
+        let arena: Arena<Root![GcVec<'_, Gc<'_, usize>>]> =
+            Arena::new_with_config(config, |mu| GcVec::new(mu));
+
+        for i in 0..50 {
+            arena.mutate(|mu, root| {
+                for j in 0..i % 5 {
+                    root.push(mu, Gc::new(mu, j));
+
+                    if mu.gc_yield() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        let metrics = arena.metrics();
+
+        (metrics.get_minor_collections(), metrics.get_major_collections())
+    };
+
+    let (minor, major) = run(0x5eed);
+
+    assert!(minor > 0 && major > 0);
+    #[cfg(not(feature = "multi_threaded"))]
+    assert_eq!(run(0x5eed), (minor, major));
+
+    let invalid = Config::builder()
+        .stress_mode(Some(StressMode::random(0, 0)))
+        .build();
+
+    assert_eq!(invalid.unwrap_err(), sandpit::ConfigError::ZeroStressRate);
+}